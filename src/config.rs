@@ -0,0 +1,273 @@
+// Tunable values for the bot's behaviour. Changing any of these requires a rebuild.
+
+use screeps::{Part, ResourceType, RoomName};
+
+/// Draws debugging visuals such as each travelling creep's cached path. Visuals cost CPU, so leave
+/// this off unless you're watching a room.
+pub const DEBUG_VISUALS: bool = false;
+
+/// Builders working on a construction site within this range of a container will wait for the
+/// container to be refilled instead of walking back to storage. Set to 0 to disable.
+pub const BUILDER_CONTAINER_RANGE: u32 = 3;
+
+/// Builders waiting on an empty container give up after this many ticks, in case nothing is
+/// refilling it
+pub const BUILDER_CONTAINER_WAIT_TICKS: u32 = 50;
+
+/// Ramparts built over roads within this many tiles of a room exit are made public so allies can
+/// pass through. All other ramparts are kept private.
+pub const PUBLIC_RAMPART_EXIT_RANGE: u8 = 2;
+
+/// One builder is spawned for every this many construction sites in a room, up to `MAX_BUILDERS`
+pub const CONSTRUCTION_SITES_PER_BUILDER: u32 = 5;
+pub const MAX_BUILDERS: u32 = 3;
+
+/// Creeps skip tasks whose estimated travel time exceeds this fraction of their remaining life
+pub const MAX_TRAVEL_TTL_FRACTION: f32 = 0.5;
+
+/// Boosts applied to combat creeps before they leave home. Creeps only wait for a boost if a lab
+/// in their room is stocked with the compound.
+pub const COMBAT_BOOSTS: &[(Part, ResourceType)] = &[
+    (Part::Attack, ResourceType::UtriumHydride),
+    (Part::RangedAttack, ResourceType::KeaniumOxide),
+    (Part::Heal, ResourceType::LemergiumOxide),
+    (Part::Tough, ResourceType::GhodiumOxide),
+];
+
+/// Non-energy resources are moved from storage to the terminal until the terminal holds this much
+/// of each
+pub const TERMINAL_RESOURCE_CAP: u32 = 10_000;
+
+/// One defender is spawned for every this many hostile attack, ranged attack and heal parts in a
+/// room, up to `MAX_DEFENDERS`
+pub const THREAT_LEVEL_PER_DEFENDER: u32 = 10;
+pub const MAX_DEFENDERS: u32 = 4;
+
+/// Invaders are counted separately from other players' creeps and never get more than this many
+/// defenders, since towers can usually deal with them
+pub const MAX_INVADER_DEFENDERS: u32 = 1;
+
+/// One mineral courier is spawned for every this much mineral waiting in the containers by a
+/// room's mineral, up to `MAX_MINERAL_COURIERS`
+pub const MINERAL_PER_COURIER: u32 = 1_000;
+pub const MAX_MINERAL_COURIERS: u32 = 2;
+
+/// Creeps attacking a hostile creep give up once it's moved further than this from where it was
+/// first seen or has left that room, rather than chasing it into territory the room can't defend
+pub const MAX_CHASE_RANGE: u32 = 10;
+
+/// Creeps attacking a hostile creep switch to any hostile healer within this range, so the healer
+/// can't keep its group alive
+pub const HEALER_FOCUS_RANGE: u32 = 3;
+
+/// Ramparts within this range of a hostile creep are held by rampart defenders, who attack
+/// anything that comes adjacent without stepping off the rampart
+pub const RAMPART_DEFENCE_RANGE: u32 = 3;
+
+/// Melee creeps kept alive while an `attack` flag is placed. The flag's name picks what they go
+/// after first: `attack:tower` (the default), `attack:spawn` or `attack:all`.
+pub const SIEGE_CREEP_COUNT: u32 = 2;
+
+/// Core attackers sent from the closest room with a spawn to clear each invader core in a remote
+/// mining room
+pub const INVADER_CORE_SQUAD_SIZE: u32 = 2;
+
+/// Siege creeps wait at home until their attack parts add up to this many for every tower in the
+/// target room, plus one for every this many hits of its strongest rampart. Boosted parts count
+/// for as much as the boost multiplies them by.
+pub const SIEGE_ATTACK_PARTS_PER_TOWER: u32 = 10;
+pub const SIEGE_RAMPART_HITS_PER_ATTACK_PART: u32 = 100_000;
+
+/// Queued terminal transfers are held until at least this much is requested, since the energy
+/// cost of a send is proportional to the amount but small sends add up
+pub const MIN_TERMINAL_SEND_AMOUNT: u32 = 1_000;
+
+/// Towers only repair walls and ramparts when there are no hostiles and storage holds at least
+/// this much energy
+pub const TOWER_DEFENCE_REPAIR_MIN_STORAGE_ENERGY: u32 = 50_000;
+
+/// Towers repair structures with fewer hits than this that are also below this fraction of their
+/// max hits. Both can be changed from the console with `set_tower_repair_limits`.
+pub const TOWER_REPAIR_MAX_HITS: u32 = 100_000;
+pub const TOWER_REPAIR_HITS_RATIO: f32 = 0.8;
+
+/// Towers get one fill task for every this much missing energy, so large deficits are split
+/// between several carriers
+pub const TOWER_FILL_ENERGY_PER_CARRIER: u32 = 200;
+
+/// Share of a room's workers that are given construction sites at once. The rest of the energy goes
+/// to upgrading the controller. Raise it while a room is still being built up and lower it once
+/// the room only needs maintaining. Dedicated builders always get construction sites.
+pub const BUILD_ENERGY_SHARE: f32 = 0.5;
+
+/// Rooms generate one repair task for every this many workers, within the min and max bounds
+pub const WORKERS_PER_REPAIR_TASK: u32 = 2;
+pub const MIN_REPAIR_TASKS: u32 = 1;
+pub const MAX_REPAIR_TASKS: u32 = 8;
+
+/// Logs every task list the named creep is offered during assignment, and why it picked the one it
+/// did. Only one creep is traced so the rest of the logs stay readable.
+pub const TRACE_ASSIGNMENT_CREEP: Option<&str> = None;
+
+/// Creeps prefer another task of the same type as their last one if it's within this range, even
+/// if a higher priority task is waiting elsewhere
+pub const TASK_AFFINITY_RANGE: u32 = 10;
+
+/// Containers within this range of the controller are kept stocked by haulers for upgraders to
+/// share. Each one supports `UPGRADERS_PER_CONTROLLER_CONTAINER` upgraders on top of the ones fed
+/// by controller links.
+pub const CONTROLLER_CONTAINER_RANGE: u32 = 3;
+pub const UPGRADERS_PER_CONTROLLER_CONTAINER: u32 = 4;
+
+/// Upgraders waiting on an empty controller link give up after this many ticks and look for
+/// energy in storage or a container instead, in case nothing is feeding the link
+pub const UPGRADER_LINK_WAIT_TICKS: u32 = 50;
+
+/// Rooms with at least this much construction progress left to build are limited to
+/// `MAX_UPGRADERS_WHILE_BUILDING` upgraders, so the energy goes to builders instead
+pub const HEAVY_CONSTRUCTION_PROGRESS: u32 = 10_000;
+pub const MAX_UPGRADERS_WHILE_BUILDING: u32 = 1;
+
+/// At RCL8, controller links and containers at least this full have energy moved back to storage
+pub const CONTROLLER_OVERFLOW_FILL_RATIO: f32 = 0.9;
+
+/// Roads are only repaired up to this fraction of their max hits, since they decay again anyway
+pub const ROAD_REPAIR_HITS_RATIO: f32 = 0.8;
+
+/// Roads, containers and ramparts that their next decay will destroy are repaired before anything
+/// else once that decay is this many ticks away
+pub const DECAY_REPAIR_TICKS: u32 = 50;
+
+/// Creeps with spare work parts repair roads and containers they pass that are below this fraction
+/// of their max hits
+pub const PASSING_REPAIR_HITS_RATIO: f32 = 0.8;
+
+/// Dropped energy piles smaller than this are left on the ground
+pub const MIN_PICKUP_AMOUNT: u32 = 50;
+
+/// Sources within this range of a live source keeper, or of a lair whose keeper spawns within
+/// `KEEPER_SPAWN_WARNING_TICKS`, are not harvested
+pub const KEEPER_DANGER_RANGE: u32 = 5;
+pub const KEEPER_SPAWN_WARNING_TICKS: u32 = 20;
+
+/// Harvesters that find every source slot taken wait this many tiles from the least busy source
+pub const SOURCE_QUEUE_RANGE: u8 = 3;
+
+/// Links within this range of a source are treated as source links. Any further and a harvester
+/// can't stand next to both the source and the link.
+pub const SOURCE_LINK_RANGE: u32 = 2;
+
+/// Spawns wait until they're full of energy before spawning more workers once a room has this many
+/// workers and haulers to refill them. Below it, spawns spend whatever energy they have to recover
+/// the room.
+pub const MIN_CARRIERS_BEFORE_WAITING: u32 = 3;
+
+/// Upgraders aren't spawned in a room until it has as many of each of these roles as its spawn
+/// goals ask for. Upgrading before the harvesters are in place stalls the room's economy.
+pub const UPGRADER_PREREQUISITE_ROLES: &[&str] = &["source_harvester"];
+
+/// Rooms with fewer creeps than this are rebuilding after a reset. They spawn workers before
+/// anything else and never wait for the spawn to fill.
+pub const BOOTSTRAP_CREEP_COUNT: u32 = 3;
+
+/// Creeps whose task is cancelled are given one of the room's leftover tasks in the same tick, as
+/// long as the tick has used less than this much CPU. Otherwise they wait for the next tick.
+pub const SAME_TICK_REASSIGN_CPU_LIMIT: f64 = 15.0;
+
+/// Creeps that go this many ticks without finding a task are recycled, unless they're the last of
+/// their role in the room
+pub const IDLE_TICKS_BEFORE_RECYCLING: u32 = 300;
+
+/// Idle creeps within this range of a spawn are renewed while the room's energy is full, as long
+/// as they have fewer than this many ticks left to live
+pub const RENEW_RANGE: u32 = 5;
+pub const RENEW_BELOW_TICKS_TO_LIVE: u32 = 1_200;
+
+/// Spawn goal counts have to stay higher for this many ticks before more creeps are spawned, so
+/// goals that flicker with energy levels don't spawn creeps that are soon unneeded
+pub const SPAWN_GOAL_SETTLE_TICKS: u32 = 20;
+
+/// Defence roles follow the threat level rather than energy, so their counts take effect straight
+/// away instead of waiting `SPAWN_GOAL_SETTLE_TICKS`
+pub const UNSETTLED_SPAWN_GOALS: &[&str] = &["melee", "rampart_defender"];
+
+/// Rooms next to an owned room that haven't been seen for this many ticks get a scout sent to them
+pub const INTEL_STALE_TICKS: u32 = 5_000;
+
+/// Roles whose replacements are spawned early, so the replacement arrives as the old creep dies
+pub const PRESPAWN_ROLES: &[&str] = &["source_harvester"];
+
+/// Rooms stop spawning once this many creeps are in them, whatever their spawn goals ask for
+pub const MAX_CREEPS_PER_ROOM: u32 = 30;
+
+/// How often, in ticks, a summary of every room's resources is logged
+pub const RESOURCE_SUMMARY_INTERVAL: u32 = 100;
+
+/// Sources at least this many tiles from storage are suggested for a link in the resource summary,
+/// if the room can still build one
+pub const LINK_RECOMMENDATION_MIN_PATH: u32 = 15;
+
+/// Construction sites that make no progress for this many ticks are assumed to be unreachable and
+/// removed
+pub const STUCK_CONSTRUCTION_SITE_TICKS: u32 = 5_000;
+
+/// How often, in ticks, rooms run the planner to place new construction sites
+pub const PLANNER_INTERVAL: u32 = 100;
+
+/// Once fewer than this many construction sites are left under the global limit, the planner only
+/// places high value structures such as spawns, extensions and towers
+pub const CONSTRUCTION_SITE_RESERVE: u32 = 20;
+
+/// Rooms listed here are safe core rooms that shouldn't spend energy on walls and defenders. Every
+/// other room is treated as a frontier room.
+pub const ECONOMY_ROOMS: &[&str] = &[];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoomMode {
+    Economy,
+    Frontier,
+}
+
+impl RoomMode {
+    pub fn max_wall_hits(&self) -> u32 {
+        match self {
+            RoomMode::Economy => 0,
+            RoomMode::Frontier => 25_000,
+        }
+    }
+
+    pub fn max_rampart_hits(&self) -> u32 {
+        match self {
+            RoomMode::Economy => 10_000,
+            RoomMode::Frontier => 100_000,
+        }
+    }
+
+    pub fn max_defenders(&self) -> u32 {
+        match self {
+            RoomMode::Economy => 1,
+            RoomMode::Frontier => MAX_DEFENDERS,
+        }
+    }
+
+    pub fn workers_per_repair_task(&self) -> u32 {
+        match self {
+            RoomMode::Economy => WORKERS_PER_REPAIR_TASK * 2,
+            RoomMode::Frontier => WORKERS_PER_REPAIR_TASK,
+        }
+    }
+}
+
+pub fn get_room_mode(room_name: RoomName) -> RoomMode {
+    if ECONOMY_ROOMS.contains(&room_name.to_string().as_str()) {
+        RoomMode::Economy
+    } else {
+        RoomMode::Frontier
+    }
+}
+
+/// Cached paths are thrown away and recalculated after this many failed moves in a row
+pub const PATH_FAILURE_LIMIT: u32 = 3;
+
+/// A creep that hasn't moved along a cached route for this many ticks has the route recalculated
+pub const ROUTE_STUCK_LIMIT: u32 = 3;
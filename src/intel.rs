@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use log::*;
+use screeps::{game, OwnedStructureProperties, RoomName, StructureObject};
+
+use crate::config;
+use crate::metadata::RoomInfo;
+
+/// What we last saw of a room. Rooms are only visible while we have something in them, so this is
+/// kept between ticks to remember rooms we've passed through.
+#[derive(Clone, Debug)]
+pub struct RoomIntel {
+    pub owner: Option<String>,
+    pub hostile: bool,
+    pub updated_at: u32,
+    pub tower_count: u32,
+    pub max_rampart_hits: u32,
+}
+
+/// Records the owner of every visible room. Rooms owned by another player are flagged hostile,
+/// since their towers will kill anything passing through.
+pub fn update_room_intel(room_info_map: &HashMap<RoomName, RoomInfo>) {
+    super::ROOM_INTEL.with(|intel| {
+        let mut intel = intel.borrow_mut();
+        for (room_name, room_info) in room_info_map.iter() {
+            let controller = room_info.controller.as_ref();
+            let room_intel = RoomIntel {
+                owner: controller.and_then(|c| c.owner()).map(|o| o.username()),
+                hostile: controller
+                    .map(|c| c.owner().is_some() && !c.my())
+                    .unwrap_or(false),
+                updated_at: game::time(),
+                tower_count: room_info
+                    .structures
+                    .iter()
+                    .filter(|s| matches!(s, StructureObject::StructureTower(tower) if !tower.my()))
+                    .count() as u32,
+                max_rampart_hits: room_info
+                    .structures
+                    .iter()
+                    .filter_map(|s| match s {
+                        StructureObject::StructureRampart(rampart) if !rampart.my() => {
+                            Some(rampart.hits())
+                        }
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or(0),
+            };
+
+            let was_hostile = intel.get(room_name).map(|i| i.hostile).unwrap_or(false);
+            if room_intel.hostile && !was_hostile {
+                info!(
+                    "room {} is owned by {}, avoiding it",
+                    room_name,
+                    room_intel.owner.clone().unwrap_or_default()
+                );
+            }
+
+            intel.insert(*room_name, room_intel);
+        }
+    });
+}
+
+pub fn is_hostile(room_name: RoomName) -> bool {
+    super::ROOM_INTEL.with(|intel| {
+        intel
+            .borrow()
+            .get(&room_name)
+            .map(|i| i.hostile)
+            .unwrap_or(false)
+    })
+}
+
+/// Returns how many attack parts a squad needs to siege the room, going by the towers and ramparts
+/// last seen there. Returns None for rooms that have never been seen.
+pub fn get_siege_attack_parts_needed(room_name: RoomName) -> Option<u32> {
+    super::ROOM_INTEL.with(|intel| {
+        intel.borrow().get(&room_name).map(|i| {
+            i.tower_count * config::SIEGE_ATTACK_PARTS_PER_TOWER
+                + i.max_rampart_hits
+                    .div_ceil(config::SIEGE_RAMPART_HITS_PER_ATTACK_PART)
+        })
+    })
+}
+
+/// Returns true if a squad with this much attack power is strong enough to siege the room. Rooms
+/// that have never been seen are assumed to be undefended, since the squad is the only way to find
+/// out. Logs whenever the verdict changes so it's clear why the squad is waiting at home.
+pub fn is_siege_squad_ready(room_name: RoomName, attack_power: u32) -> bool {
+    let needed = get_siege_attack_parts_needed(room_name);
+    let ready = needed.map(|needed| attack_power >= needed).unwrap_or(true);
+
+    let was_ready = super::SIEGE_READINESS
+        .with(|readiness| readiness.borrow_mut().insert(room_name, ready))
+        .unwrap_or(true);
+    if !ready && was_ready {
+        info!(
+            "not sieging {}: squad has {} attack power but its defences need {}, spawn more or boosted creeps",
+            room_name,
+            attack_power,
+            needed.unwrap_or_default()
+        );
+    } else if ready && !was_ready {
+        info!("squad is strong enough to siege {}", room_name);
+    }
+
+    ready
+}
+
+/// Returns the rooms next to the room that haven't been seen in `INTEL_STALE_TICKS`, least
+/// recently seen first. Rooms that have never been seen come first of all.
+pub fn get_stale_neighbours(room_name: RoomName) -> Vec<RoomName> {
+    let time = game::time();
+    let mut neighbours = game::map::describe_exits(room_name)
+        .values()
+        .filter_map(|neighbour| {
+            let updated_at = super::ROOM_INTEL
+                .with(|intel| intel.borrow().get(&neighbour).map(|i| i.updated_at));
+            match updated_at {
+                Some(updated_at) if time - updated_at < config::INTEL_STALE_TICKS => None,
+                _ => Some((neighbour, updated_at)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    neighbours.sort_by_key(|(_, updated_at)| *updated_at);
+    neighbours
+        .into_iter()
+        .map(|(neighbour, _)| neighbour)
+        .collect()
+}
+
+/// Returns true if we remote mine the room: it isn't ours, and it's either reserved by us or next
+/// to a room we own
+pub fn is_remote_mining_room(room_info: &RoomInfo) -> bool {
+    let controller = room_info.controller.as_ref();
+    if controller.is_some_and(|c| c.my()) {
+        return false;
+    }
+
+    let my_username = game::spawns()
+        .values()
+        .find_map(|spawn| spawn.owner())
+        .map(|owner| owner.username());
+    let reserved_by_us = controller
+        .and_then(|c| c.reservation())
+        .is_some_and(|reservation| Some(reservation.username()) == my_username);
+
+    reserved_by_us
+        || game::map::describe_exits(room_info.room.name())
+            .values()
+            .any(|neighbour| {
+                game::rooms()
+                    .get(neighbour)
+                    .and_then(|room| room.controller())
+                    .is_some_and(|c| c.my())
+            })
+}
+
+/// Returns the invader cores in the room that a squad can clear. Only cores in remote mining rooms
+/// are worth the trip, and cores above level 0 belong to strongholds that are too strong to take
+/// on.
+pub fn get_clearable_invader_cores(room_info: &RoomInfo) -> Vec<&StructureObject> {
+    if !is_remote_mining_room(room_info) {
+        return vec![];
+    }
+
+    room_info
+        .invader_cores
+        .iter()
+        .filter(|s| matches!(s, StructureObject::StructureInvaderCore(core) if core.level() == 0))
+        .collect()
+}
+
+/// Returns the visible rooms with an invader core a squad should clear
+pub fn get_invader_core_rooms(room_info_map: &HashMap<RoomName, RoomInfo>) -> Vec<RoomName> {
+    room_info_map
+        .iter()
+        .filter(|(_, room_info)| !get_clearable_invader_cores(room_info).is_empty())
+        .map(|(room_name, _)| *room_name)
+        .collect()
+}
+
+/// Returns the room with one of our spawns closest to the room, which sends squads there
+pub fn get_closest_spawn_room(room_name: RoomName) -> Option<RoomName> {
+    game::spawns()
+        .values()
+        .filter_map(|spawn| spawn.room())
+        .map(|room| room.name())
+        .min_by_key(|spawn_room| game::map::get_room_linear_distance(room_name, *spawn_room, false))
+}
@@ -1,18 +1,28 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use log::*;
 use screeps::{constants::Part, enums::StructureObject, find, game};
-use screeps::{HasPosition, ResourceType, RoomName, StructureProperties, StructureType};
+use screeps::{
+    ConstructionSite, HasPosition, HasStore, ObjectId, Position, ResourceType, RoomName, Source,
+    StructureLink, StructureProperties, StructureType,
+};
 use spawn::{SpawnGoal, SpawnGoals, SpawnManager};
 use tasks::TaskManager;
 use wasm_bindgen::prelude::*;
 
+mod config;
+mod intel;
 mod logging;
+mod logistics;
 mod metadata;
 mod pathing;
+mod planner;
+mod ramparts;
+mod report;
 mod spawn;
 mod tasks;
+mod terminal;
 mod utils;
 
 // add wasm_bindgen to any function you would like to expose for call from js
@@ -21,6 +31,92 @@ pub fn setup() {
     logging::setup_logging(logging::Trace);
 }
 
+/// Drops the named creep's current task list so it gets reassigned next tick
+#[wasm_bindgen]
+pub fn reset_creep_task(creep_name: &str) {
+    TASK_MANAGER.with(|task_manager| {
+        task_manager.borrow_mut().reset_creep_task(creep_name);
+    });
+}
+
+/// Logs a snapshot of every creep's task and the task manager's bookkeeping
+#[wasm_bindgen]
+pub fn dump_tasks() {
+    TASK_MANAGER.with(|task_manager| {
+        task_manager.borrow().dump_tasks();
+    });
+}
+
+/// Queues a terminal transfer of the resource to the room from whichever room can send it cheapest
+#[wasm_bindgen]
+pub fn request_resource(room_name: &str, resource: ResourceType, amount: u32) {
+    match RoomName::new(room_name) {
+        Ok(room_name) => terminal::request_transfer(room_name, resource, amount),
+        Err(_) => error!("invalid room name: {}", room_name),
+    }
+}
+
+/// Stops tasks, spawning, towers and planning in the room so it can be managed by hand
+#[wasm_bindgen]
+pub fn disable_room(room_name: &str) {
+    match RoomName::new(room_name) {
+        Ok(room_name) => {
+            DISABLED_ROOMS.with(|rooms| rooms.borrow_mut().insert(room_name));
+            info!("automation disabled in room {}", room_name);
+        }
+        Err(_) => error!("invalid room name: {}", room_name),
+    }
+}
+
+/// Hands a room disabled with `disable_room` back to the bot
+#[wasm_bindgen]
+pub fn enable_room(room_name: &str) {
+    match RoomName::new(room_name) {
+        Ok(room_name) => {
+            DISABLED_ROOMS.with(|rooms| rooms.borrow_mut().remove(&room_name));
+            info!("automation enabled in room {}", room_name);
+        }
+        Err(_) => error!("invalid room name: {}", room_name),
+    }
+}
+
+/// Spawns one creep of the role at the room's next idle spawn, whatever the role's spawn goal
+/// count is. The role's body comes from its spawn goal.
+#[wasm_bindgen]
+pub fn force_spawn(room_name: &str, role: &str) {
+    match RoomName::new(room_name) {
+        Ok(room_name) => {
+            FORCED_SPAWNS.with(|spawns| spawns.borrow_mut().push((room_name, role.to_string())));
+            info!("queued a {} to spawn in room {}", role, room_name);
+        }
+        Err(_) => error!("invalid room name: {}", room_name),
+    }
+}
+
+/// Empties the room's storage and terminal into the nearest other owned room with a storage, then
+/// recycles the creeps that belonged to it. The room stops spawning while it's evacuated. Unclaim
+/// it once its creeps are gone.
+#[wasm_bindgen]
+pub fn evacuate_room(room_name: &str) {
+    match RoomName::new(room_name) {
+        Ok(room_name) => {
+            EVACUATING_ROOMS.with(|rooms| rooms.borrow_mut().insert(room_name));
+            info!("evacuating room {}", room_name);
+        }
+        Err(_) => error!("invalid room name: {}", room_name),
+    }
+}
+
+/// Overrides `TOWER_REPAIR_MAX_HITS` and `TOWER_REPAIR_HITS_RATIO` until the next global reset
+#[wasm_bindgen]
+pub fn set_tower_repair_limits(max_hits: u32, hits_ratio: f32) {
+    TOWER_REPAIR_LIMITS.with(|limits| *limits.borrow_mut() = (max_hits, hits_ratio));
+    info!(
+        "towers repair structures below {} hits and {} of their max hits",
+        max_hits, hits_ratio
+    );
+}
+
 // this is one way to persist data between ticks within Rust's memory, as opposed to
 // keeping state in memory on game objects - but will be lost on global resets!
 thread_local! {
@@ -29,6 +125,22 @@ thread_local! {
     static PAUSE_SCRIPT: RefCell<bool> = RefCell::new(false);
     static LAST_CPU_USAGE: RefCell<f64> = RefCell::new(0_f64);
     static AVERAGE_CPU_USAGE_X_TICKS: RefCell<Vec<f64>> = RefCell::new(Vec::new());
+    static STRUCTURE_CACHE: RefCell<HashMap<RoomName, (u32, HashMap<StructureType, Vec<StructureObject>>)>> = RefCell::new(HashMap::new());
+    static SOURCE_LINK_CACHE: RefCell<HashMap<RoomName, (u32, Vec<(ObjectId<StructureLink>, ObjectId<Source>)>)>> = RefCell::new(HashMap::new());
+    static TERMINAL_TRANSFERS: RefCell<HashMap<(RoomName, ResourceType), u32>> = RefCell::new(HashMap::new());
+    static CONSTRUCTION_SITE_PROGRESS: RefCell<HashMap<ObjectId<ConstructionSite>, (u32, u32)>> = RefCell::new(HashMap::new());
+    static ROUTE_CACHE: RefCell<HashMap<(Position, Position), Vec<Position>>> = RefCell::new(HashMap::new());
+    static ENERGY_STARVED_ROOMS: RefCell<HashSet<RoomName>> = RefCell::new(HashSet::new());
+    static SPAWN_GOAL_COUNTS: RefCell<HashMap<(RoomName, String), spawn::SettledCount>> = RefCell::new(HashMap::new());
+    static ROLE_TARGET_COUNTS: RefCell<HashMap<(RoomName, String), (u32, bool)>> = RefCell::new(HashMap::new());
+    static FORCED_SPAWNS: RefCell<Vec<(RoomName, String)>> = RefCell::new(Vec::new());
+    static UNAFFORDABLE_SPAWN_GOALS: RefCell<HashSet<(RoomName, String)>> = RefCell::new(HashSet::new());
+    static EVACUATING_ROOMS: RefCell<HashSet<RoomName>> = RefCell::new(HashSet::new());
+    static TOWER_REPAIR_LIMITS: RefCell<(u32, f32)> = RefCell::new((config::TOWER_REPAIR_MAX_HITS, config::TOWER_REPAIR_HITS_RATIO));
+    static DISABLED_ROOMS: RefCell<HashSet<RoomName>> = RefCell::new(HashSet::new());
+    static ROOM_INTEL: RefCell<HashMap<RoomName, intel::RoomIntel>> = RefCell::new(HashMap::new());
+    static SIEGE_READINESS: RefCell<HashMap<RoomName, bool>> = RefCell::new(HashMap::new());
+    static ROUTE_STUCK_COUNTS: RefCell<HashMap<String, (Position, u32)>> = RefCell::new(HashMap::new());
 }
 
 // to use a reserved name as a function name, use `js_name`:
@@ -58,6 +170,8 @@ pub fn game_loop() {
         utils::log_cpu_usage("clean up tasks");
         task_manager.refresh_room_info();
         utils::log_cpu_usage("refresh room info");
+        intel::update_room_intel(&task_manager.room_info_map);
+        utils::log_cpu_usage("update room intel");
         let flag_tasks_lists = task_manager.assign_tasks();
         utils::log_cpu_usage("assign tasks");
         task_manager.execute_tasks();
@@ -66,9 +180,39 @@ pub fn game_loop() {
             game::creeps().keys().count()
         ));
 
+        spawn::unblock_spawns(&task_manager);
+        utils::log_cpu_usage("unblock spawns");
+        task_manager.unblock_mining_tiles();
+        utils::log_cpu_usage("unblock mining tiles");
+
+        terminal::process_transfers(&task_manager.room_info_map);
+        utils::log_cpu_usage("process terminal transfers");
+
+        if game::time() % config::RESOURCE_SUMMARY_INTERVAL == 0 {
+            report::log_resource_summary(&task_manager.room_info_map);
+            report::log_link_recommendations(&task_manager.room_info_map);
+            report::log_misplaced_links(&task_manager.room_info_map);
+            utils::log_cpu_usage("log resource summary");
+        }
+
+        let mut construction_site_budget = if planner::is_planning_tick() {
+            planner::get_construction_site_budget()
+        } else {
+            0
+        };
         for room in rooms {
-            execute_towers(task_manager.room_info_map.get(&room.name()).unwrap());
-            utils::log_cpu_usage(&format!("execute towers in room {}", room.name()));
+            if utils::is_room_disabled(room.name()) {
+                continue;
+            }
+
+            if let Some(room_info) = task_manager.room_info_map.get(&room.name()) {
+                execute_towers(room_info);
+                utils::log_cpu_usage(&format!("execute towers in room {}", room.name()));
+                ramparts::maintain_ramparts(room_info);
+                utils::log_cpu_usage(&format!("maintain ramparts in room {}", room.name()));
+                planner::plan_room(room_info, &mut construction_site_budget);
+                utils::log_cpu_usage(&format!("plan room {}", room.name()));
+            }
         }
 
         let claim_task_exists = flag_tasks_lists.iter().any(|t| {
@@ -79,6 +223,21 @@ pub fn game_loop() {
             }
         });
 
+        let keeper_kill_task_exists = flag_tasks_lists.iter().any(|t| {
+            if let Some(task) = t.current_task() {
+                task.get_type() == tasks::TaskType::KillKeeper
+            } else {
+                false
+            }
+        });
+
+        // attack flags can be in rooms we can't see, so they're checked directly
+        let attack_flag_exists = game::flags()
+            .values()
+            .any(|f| f.name().starts_with("attack", 0));
+
+        let invader_core_rooms = intel::get_invader_core_rooms(&task_manager.room_info_map);
+
         // Spawn creeps
         let mut room_spawn_goals: HashMap<RoomName, SpawnGoals> = HashMap::new();
         for room in game::rooms().values() {
@@ -89,13 +248,22 @@ pub fn game_loop() {
             }
 
             let room_name = room.name();
+            let room_info = task_manager.room_info_map.get(&room_name);
+            if room_info.is_none() {
+                continue;
+            }
+            let room_info = room_info.unwrap();
+
             let spawn_goals = room_spawn_goals.entry(room_name).or_default();
 
-            let source_infos = &task_manager.room_info_map.get(&room_name).unwrap().sources;
-            let link_type_map = &task_manager.room_info_map.get(&room.name()).unwrap().links;
+            let source_infos = &room_info.sources;
+            let link_type_map = &room_info.links;
 
-            let source_link_has_output = !(link_type_map.storage_links.is_empty()
-                && link_type_map.controller_links.is_empty());
+            // low level rooms may still have links left over from a previous owner
+            let links_enabled = room_info.is_feature_enabled(metadata::Feature::Links);
+            let source_link_has_output = links_enabled
+                && !(link_type_map.storage_links.is_empty()
+                    && link_type_map.controller_links.is_empty());
 
             let target_worker_count = source_infos
                 .iter()
@@ -111,8 +279,22 @@ pub fn game_loop() {
 
             let source_link_count = link_type_map.source_links.len();
 
+            // sources without a link or container are drop mined once there's a storage for
+            // haulers to bring the energy to
+            let phase = room_info.phase();
+            let has_storage = phase == metadata::RoomPhase::Mature;
+            let drop_mined_source_count = if has_storage {
+                source_infos
+                    .iter()
+                    .filter(|s| !s.has_link && !s.has_container)
+                    .count() as u32
+            } else {
+                0
+            };
+
             spawn_goals.push(SpawnGoal {
                 name: "source_harvester".to_string(),
+                version: 1,
                 body: vec![
                     Part::Move,
                     Part::Move,
@@ -137,7 +319,25 @@ pub fn game_loop() {
                     source_link_count as u32
                 } else {
                     0
-                },
+                } + drop_mined_source_count,
+                is_global: false,
+            });
+
+            spawn_goals.push(SpawnGoal {
+                name: "hauler".to_string(),
+                version: 1,
+                body: vec![
+                    Part::Move,
+                    Part::Move,
+                    Part::Carry,
+                    Part::Carry,
+                    Part::Carry,
+                    Part::Carry,
+                ],
+                body_upgrades: vec![Part::Move, Part::Carry, Part::Carry],
+                max_body_upgrades: 4,
+                source_modifier: 0,
+                count: drop_mined_source_count,
                 is_global: false,
             });
 
@@ -159,7 +359,7 @@ pub fn game_loop() {
                 Part::Carry,
                 Part::Carry,
             ];
-            for _ in 0..source_link_count {
+            for _ in 0..std::cmp::max(source_link_count, 1) {
                 body.append(&mut vec![
                     Part::Work,
                     Part::Work,
@@ -173,17 +373,35 @@ pub fn game_loop() {
                     Part::Work,
                 ]);
             }
+
+            let link_upgrader_count =
+                if links_enabled && source_link_count > 0 && controller_link_energy > 400 {
+                    controller_link_count as u32
+                } else {
+                    0
+                };
+            // containers are refilled from storage, so they only add upgraders once there is one
+            let container_upgrader_count = if has_storage {
+                room_info.get_controller_containers().len() as u32
+                    * config::UPGRADERS_PER_CONTROLLER_CONTAINER
+            } else {
+                0
+            };
+
+            // heavy construction gets the energy upgraders would otherwise spend
+            let mut upgrader_count = link_upgrader_count + container_upgrader_count;
+            if room_info.get_remaining_construction() >= config::HEAVY_CONSTRUCTION_PROGRESS {
+                upgrader_count = upgrader_count.min(config::MAX_UPGRADERS_WHILE_BUILDING);
+            }
+
             spawn_goals.push(SpawnGoal {
                 name: "upgrader".to_string(),
+                version: 1,
                 body,
                 body_upgrades: vec![],
                 max_body_upgrades: 0,
                 source_modifier: 0,
-                count: if source_link_count > 0 && controller_link_energy > 400 {
-                    controller_link_count as u32
-                } else {
-                    0
-                },
+                count: upgrader_count,
                 is_global: false,
             });
 
@@ -197,6 +415,7 @@ pub fn game_loop() {
                 .sum::<u32>();
             spawn_goals.push(SpawnGoal {
                 name: "storager".to_string(),
+                version: 1,
                 body: vec![
                     Part::Move,
                     Part::Move,
@@ -209,7 +428,7 @@ pub fn game_loop() {
                 body_upgrades: vec![],
                 max_body_upgrades: 0,
                 source_modifier: 0,
-                count: if source_link_count > 0 && storage_link_energy > 400 {
+                count: if links_enabled && source_link_count > 0 && storage_link_energy > 400 {
                     storage_link_count as u32
                 } else {
                     0
@@ -219,6 +438,7 @@ pub fn game_loop() {
 
             spawn_goals.push(SpawnGoal {
                 name: "worker".to_string(),
+                version: 1,
                 body: vec![Part::Move, Part::Move, Part::Carry, Part::Work],
                 body_upgrades: vec![Part::Move, Part::Carry, Part::Work],
                 max_body_upgrades: 4,
@@ -227,18 +447,156 @@ pub fn game_loop() {
                 is_global: false,
             });
 
+            // minerals are carried to the terminal to be sold
+            let has_terminal = room_info.is_feature_enabled(metadata::Feature::Terminal)
+                && room_info
+                    .my_structures
+                    .iter()
+                    .any(|s| s.structure_type() == StructureType::Terminal);
+            let waiting_mineral_amount = room_info
+                .get_mineral_containers()
+                .iter()
+                .map(|container| {
+                    let store = container.store();
+                    store.get_used_capacity(None)
+                        - store.get_used_capacity(Some(ResourceType::Energy))
+                })
+                .sum::<u32>();
+            // an exhausted mineral gets no harvesters until it regenerates
+            let has_harvestable_mineral = room_info
+                .get_extracted_mineral()
+                .is_some_and(|mineral| mineral.mineral_amount() > 0)
+                && !room_info.get_mineral_containers().is_empty();
+            spawn_goals.push(SpawnGoal {
+                name: "mineral_harvester".to_string(),
+                version: 1,
+                body: vec![Part::Move, Part::Work, Part::Work],
+                body_upgrades: vec![Part::Move, Part::Work, Part::Work],
+                max_body_upgrades: 5,
+                source_modifier: 0,
+                count: if has_terminal && has_harvestable_mineral {
+                    1
+                } else {
+                    0
+                },
+                is_global: false,
+            });
+
+            spawn_goals.push(SpawnGoal {
+                name: "mineral_courier".to_string(),
+                version: 1,
+                body: vec![
+                    Part::Move,
+                    Part::Move,
+                    Part::Carry,
+                    Part::Carry,
+                    Part::Carry,
+                    Part::Carry,
+                ],
+                body_upgrades: vec![Part::Move, Part::Carry, Part::Carry],
+                max_body_upgrades: 4,
+                source_modifier: 0,
+                count: if has_terminal {
+                    std::cmp::min(
+                        waiting_mineral_amount.div_ceil(config::MINERAL_PER_COURIER),
+                        config::MAX_MINERAL_COURIERS,
+                    )
+                } else {
+                    0
+                },
+                is_global: false,
+            });
+
+            let construction_site_count = room_info.construction_sites.len() as u32;
+            spawn_goals.push(SpawnGoal {
+                name: "builder".to_string(),
+                version: 1,
+                body: vec![Part::Move, Part::Move, Part::Carry, Part::Work],
+                body_upgrades: vec![Part::Move, Part::Carry, Part::Work],
+                max_body_upgrades: 4,
+                source_modifier: 0,
+                // bootstrapping rooms put everything into reaching RCL2 first
+                count: if phase == metadata::RoomPhase::Bootstrap {
+                    0
+                } else {
+                    std::cmp::min(
+                        construction_site_count.div_ceil(config::CONSTRUCTION_SITES_PER_BUILDER),
+                        config::MAX_BUILDERS,
+                    )
+                },
+                is_global: false,
+            });
+
+            let invader_defender_count = std::cmp::min(
+                room_info
+                    .invader_threat_level
+                    .div_ceil(config::THREAT_LEVEL_PER_DEFENDER),
+                config::MAX_INVADER_DEFENDERS,
+            );
+            let defender_count = std::cmp::min(
+                room_info
+                    .threat_level
+                    .div_ceil(config::THREAT_LEVEL_PER_DEFENDER)
+                    .max(invader_defender_count),
+                config::get_room_mode(room_name).max_defenders(),
+            );
+            // each remote invader core is cleared by a squad from the closest room with a spawn
+            let invader_core_attacker_count = invader_core_rooms
+                .iter()
+                .filter(|core_room| intel::get_closest_spawn_room(**core_room) == Some(room_name))
+                .count() as u32
+                * config::INVADER_CORE_SQUAD_SIZE;
+            let siege_creep_count = if attack_flag_exists {
+                config::SIEGE_CREEP_COUNT
+            } else {
+                0
+            };
+
+            // creeps are boosted before they leave home, so a stocked lab lets them spawn with
+            // fewer upgrades and make up the difference with the boost. The lab has to hold
+            // enough for the most attack parts (a full rampart defender) and heal parts (a full
+            // keeper killer) any of these bodies can have.
+            let attack_boost_multiplier = room_info.get_stocked_boost_multiplier(Part::Attack, 16);
+            let heal_boost_multiplier = room_info.get_stocked_boost_multiplier(Part::Heal, 6);
+
             spawn_goals.push(SpawnGoal {
                 name: "melee".to_string(),
+                version: 1,
                 body: vec![Part::Move, Part::Move, Part::Attack, Part::Attack],
-                body_upgrades: vec![],
-                max_body_upgrades: 0,
-                count: 0,
+                body_upgrades: vec![Part::Move, Part::Attack],
+                max_body_upgrades: 6 / attack_boost_multiplier,
+                count: defender_count.max(siege_creep_count),
                 source_modifier: 0,
                 is_global: true, // TODO: Fix defend flag mechanic
             });
 
+            spawn_goals.push(SpawnGoal {
+                name: "core_attacker".to_string(),
+                version: 1,
+                body: vec![Part::Move, Part::Move, Part::Attack, Part::Attack],
+                body_upgrades: vec![Part::Move, Part::Attack],
+                max_body_upgrades: 6 / attack_boost_multiplier,
+                count: invader_core_attacker_count,
+                source_modifier: 0,
+                is_global: false,
+            });
+
+            // walled rooms hold their ramparts instead of sending melee creeps out to chase
+            let threatened_rampart_count = room_info.get_threatened_ramparts().len() as u32;
+            spawn_goals.push(SpawnGoal {
+                name: "rampart_defender".to_string(),
+                version: 1,
+                body: vec![Part::Move, Part::Attack, Part::Attack],
+                body_upgrades: vec![Part::Move, Part::Attack, Part::Attack],
+                max_body_upgrades: 7 / attack_boost_multiplier,
+                source_modifier: 0,
+                count: std::cmp::min(defender_count, threatened_rampart_count),
+                is_global: false,
+            });
+
             spawn_goals.push(SpawnGoal {
                 name: "claimer".to_string(),
+                version: 1,
                 body: vec![Part::Move, Part::Claim],
                 body_upgrades: vec![],
                 max_body_upgrades: 0,
@@ -247,6 +605,41 @@ pub fn game_loop() {
                 is_global: true,
             });
 
+            spawn_goals.push(SpawnGoal {
+                name: "keeper_killer".to_string(),
+                version: 1,
+                body: vec![
+                    Part::Move,
+                    Part::Move,
+                    Part::Move,
+                    Part::Move,
+                    Part::Attack,
+                    Part::Attack,
+                    Part::Attack,
+                    Part::Heal,
+                ],
+                body_upgrades: vec![Part::Move, Part::Attack, Part::Move, Part::Heal],
+                max_body_upgrades: 5 / attack_boost_multiplier.min(heal_boost_multiplier),
+                source_modifier: 0,
+                count: if keeper_kill_task_exists { 1 } else { 0 },
+                is_global: true,
+            });
+
+            spawn_goals.push(SpawnGoal {
+                name: "scout".to_string(),
+                version: 1,
+                body: vec![Part::Move],
+                body_upgrades: vec![],
+                max_body_upgrades: 0,
+                source_modifier: 0,
+                count: if intel::get_stale_neighbours(room_name).is_empty() {
+                    0
+                } else {
+                    1
+                },
+                is_global: false,
+            });
+
             // info!("spawn goals for room {}: {:?}", room_name, spawn_goals);
         }
         utils::log_cpu_usage("calculate spawn goals");
@@ -293,7 +686,7 @@ pub fn game_loop() {
 fn execute_towers(room_info: &metadata::RoomInfo) {
     let structures = &room_info.structures;
     let my_structures = &room_info.my_structures;
-    let mut enemies = room_info.room.find(find::HOSTILE_CREEPS, None);
+    let mut enemies = room_info.hostile_creeps.clone();
 
     let towers = my_structures
         .iter()
@@ -307,14 +700,35 @@ fn execute_towers(room_info: &metadata::RoomInfo) {
         .collect::<Vec<_>>();
     injured.sort_by_key(|a| a.hits());
 
-    // get damaged structures (anything with less than 100K hit points)
+    // tower energy is saved for defence unless the room is at peace with energy to spare
+    let storage_energy = my_structures
+        .iter()
+        .find_map(|s| {
+            if let StructureObject::StructureStorage(storage) = s {
+                return Some(
+                    storage
+                        .store()
+                        .get_used_capacity(Some(ResourceType::Energy)),
+                );
+            }
+            None
+        })
+        .unwrap_or(0);
+    let can_repair_defences =
+        enemies.is_empty() && storage_energy >= config::TOWER_DEFENCE_REPAIR_MIN_STORAGE_ENERGY;
+
+    // get damaged structures, within the repair limits set from the console
+    let (max_hits, hits_ratio) = TOWER_REPAIR_LIMITS.with(|limits| *limits.borrow());
     let mut damaged = structures
         .iter()
         .map(|s| s.as_structure())
         .filter(|s| {
-            let x = (s.hits() as f32 / s.hits_max() as f32) < 0.8;
-            let y = s.hits() < 100000;
-            let z = s.structure_type() != StructureType::Wall;
+            let x = (s.hits() as f32 / s.hits_max() as f32) < hits_ratio;
+            let y = s.hits() < max_hits;
+            let z = !matches!(
+                s.structure_type(),
+                StructureType::Wall | StructureType::Rampart
+            ) || can_repair_defences;
             x && y && z
         })
         .collect::<Vec<_>>();
@@ -322,12 +736,16 @@ fn execute_towers(room_info: &metadata::RoomInfo) {
     damaged.sort_by_key(|a| a.hits());
 
     for tower in towers {
-        // attack the closest enemy creep
+        // attack the most dangerous enemy creep, breaking ties by distance
         enemies.sort_by(|a, b| {
-            tower
-                .pos()
-                .get_range_to(a.pos())
-                .cmp(&tower.pos().get_range_to(b.pos()))
+            metadata::get_threat_level(b)
+                .cmp(&metadata::get_threat_level(a))
+                .then_with(|| {
+                    tower
+                        .pos()
+                        .get_range_to(a.pos())
+                        .cmp(&tower.pos().get_range_to(b.pos()))
+                })
         });
 
         if let Some(enemy) = enemies.first() {
@@ -0,0 +1,220 @@
+use screeps::{
+    HasPosition, HasStore, HasTypedId, OwnedStructureProperties, Position, ResourceType,
+    StructureObject,
+};
+
+use crate::config;
+use crate::metadata::{RoomInfo, StorageLink};
+use crate::tasks::{Task, TaskList, TransferTask, WithdrawTask};
+
+/// A structure carriers can take energy from, and how much of its energy hasn't been promised to a
+/// delivery yet
+struct Supply {
+    structure: StructureObject,
+    remaining: u32,
+}
+
+/// A structure that needs energy. Demands with a lower priority are met first.
+struct Demand {
+    structure: StructureObject,
+    priority: u32,
+    deficit: u32,
+    carriers_wanted: u32,
+}
+
+/// One carrier's trip from a supply to a demand. Deliveries without a supply can still be taken by
+/// creeps that already carry energy.
+pub struct Delivery {
+    pub demand: StructureObject,
+    pub supply: Option<StructureObject>,
+}
+
+/// Where energy is in a room and where it's needed. Supplies are storage, storage links and every
+/// container except the controller's. Demands are spawns, extensions, towers and, once there's
+/// storage to stock them from, controller containers.
+pub struct EnergyGraph {
+    supplies: Vec<Supply>,
+    demands: Vec<Demand>,
+}
+
+impl EnergyGraph {
+    pub fn new(room_info: &RoomInfo) -> EnergyGraph {
+        let controller_containers = room_info.get_controller_containers();
+        let storage_links = &room_info.links.storage_links;
+
+        let supplies = room_info
+            .structures
+            .iter()
+            .filter(|s| match s {
+                StructureObject::StructureStorage(storage) => storage.my(),
+                StructureObject::StructureContainer(container) => !controller_containers
+                    .iter()
+                    .any(|c| c.id() == container.id()),
+                StructureObject::StructureLink(link) => storage_links
+                    .iter()
+                    .any(|StorageLink(l, _)| l.id() == link.id()),
+                _ => false,
+            })
+            .filter_map(|s| {
+                let remaining = s
+                    .as_has_store()?
+                    .store()
+                    .get_used_capacity(Some(ResourceType::Energy));
+                if remaining == 0 {
+                    return None;
+                }
+                Some(Supply {
+                    structure: s.clone(),
+                    remaining,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let has_storage = supplies
+            .iter()
+            .any(|s| matches!(s.structure, StructureObject::StructureStorage(_)));
+
+        let mut demands = room_info
+            .structures
+            .iter()
+            .filter_map(|s| {
+                let store = s.as_has_store()?.store();
+                let capacity = store.get_capacity(Some(ResourceType::Energy));
+                let deficit = store.get_free_capacity(Some(ResourceType::Energy)).max(0) as u32;
+
+                let (priority, carriers_wanted) = match s {
+                    StructureObject::StructureSpawn(spawn) if spawn.my() && spawn.is_active() => {
+                        (0, 1)
+                    }
+                    StructureObject::StructureExtension(extension)
+                        if extension.my() && extension.is_active() =>
+                    {
+                        (0, 1)
+                    }
+                    // towers are only topped up once they drop below half, and take as many
+                    // carriers as it takes to cover their deficit so several are filled at once
+                    StructureObject::StructureTower(tower)
+                        if tower.my() && tower.is_active() && deficit > capacity / 2 =>
+                    {
+                        (1, deficit.div_ceil(config::TOWER_FILL_ENERGY_PER_CARRIER))
+                    }
+                    // controller containers are kept at least half full for upgraders
+                    StructureObject::StructureContainer(container)
+                        if has_storage
+                            && deficit > capacity / 2
+                            && controller_containers
+                                .iter()
+                                .any(|c| c.id() == container.id()) =>
+                    {
+                        (2, 1)
+                    }
+                    _ => return None,
+                };
+
+                if deficit == 0 {
+                    return None;
+                }
+
+                Some(Demand {
+                    structure: s.clone(),
+                    priority,
+                    deficit,
+                    carriers_wanted,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // within a priority, the emptiest structures come first
+        demands.sort_by_key(|d| (d.priority, std::cmp::Reverse(d.deficit)));
+
+        EnergyGraph { supplies, demands }
+    }
+
+    /// Matches the demand that isn't already being met to the supplies, most valuable demand first.
+    /// Each delivery comes from the closest supply with enough energy left for it, falling back to
+    /// the closest with any energy. `carriers_working` returns how many carriers are already headed
+    /// to a position.
+    pub fn get_deliveries<F>(mut self, carriers_working: F) -> Vec<Delivery>
+    where
+        F: Fn(Position) -> u32,
+    {
+        let mut deliveries = Vec::new();
+        for demand in self.demands.iter() {
+            let pos = demand.structure.pos();
+            let working = carriers_working(pos);
+            if working >= demand.carriers_wanted {
+                continue;
+            }
+
+            let amount_per_carrier = demand.deficit.div_ceil(demand.carriers_wanted);
+            for _ in working..demand.carriers_wanted {
+                let supply = self
+                    .supplies
+                    .iter_mut()
+                    .filter(|s| s.remaining > 0)
+                    .min_by_key(|s| {
+                        (
+                            s.remaining < amount_per_carrier,
+                            pos.get_range_to(s.structure.pos()),
+                        )
+                    });
+
+                let supply = supply.map(|supply| {
+                    supply.remaining = supply.remaining.saturating_sub(amount_per_carrier);
+                    supply.structure.clone()
+                });
+
+                deliveries.push(Delivery {
+                    demand: demand.structure.clone(),
+                    supply,
+                });
+            }
+        }
+
+        deliveries
+    }
+}
+
+impl Delivery {
+    /// Returns true if the delivery refills a spawn or extension
+    pub fn is_spawn_fill(&self) -> bool {
+        matches!(
+            self.demand,
+            StructureObject::StructureSpawn(_) | StructureObject::StructureExtension(_)
+        )
+    }
+
+    /// Returns a task list that withdraws from the supply, if there is one, and transfers to the
+    /// demand
+    pub fn to_task_list(&self) -> Option<TaskList> {
+        let transfer_task: Box<dyn Task> = match &self.demand {
+            StructureObject::StructureSpawn(spawn) => Box::new(TransferTask::new(spawn.id())),
+            StructureObject::StructureExtension(extension) => {
+                Box::new(TransferTask::new(extension.id()))
+            }
+            StructureObject::StructureTower(tower) => Box::new(TransferTask::new(tower.id())),
+            StructureObject::StructureContainer(container) => {
+                Box::new(TransferTask::new(container.id()))
+            }
+            _ => return None,
+        };
+
+        let mut tasks = vec![transfer_task];
+        match &self.supply {
+            Some(StructureObject::StructureStorage(storage)) => {
+                tasks.insert(0, Box::new(WithdrawTask::new(storage.id())))
+            }
+            Some(StructureObject::StructureContainer(container)) => {
+                tasks.insert(0, Box::new(WithdrawTask::new(container.id())))
+            }
+            Some(StructureObject::StructureLink(link)) => tasks.insert(
+                0,
+                Box::new(WithdrawTask::new(link.id()).queue_when_crowded()),
+            ),
+            _ => {}
+        }
+
+        let tasks_count = tasks.len() - 1;
+        Some(TaskList::new(tasks, false, tasks_count))
+    }
+}
@@ -1,26 +1,44 @@
+use std::collections::HashMap;
+
 use screeps::{
-    find, look::LookResult, ConstructionSite, Creep, HasPosition, MaybeHasTypedId, Room, Source,
-    StructureController, StructureLink, StructureObject, StructureSpawn, StructureStorage,
+    constants::{CONTAINER_DECAY, RAMPART_DECAY_AMOUNT, ROAD_DECAY_AMOUNT, ROAD_HITS},
+    find,
+    look::LookResult,
+    ConstructionSite, Creep, HasPosition, HasStore, HasTypedId, MaybeHasTypedId, Mineral,
+    OwnedStructureProperties, Part, Position, ResourceType, Room, SharedCreepProperties, Source,
+    StructureContainer, StructureController, StructureKeeperLair, StructureLink, StructureObject,
+    StructureProperties, StructureRampart, StructureSpawn, StructureStorage, StructureType,
 };
 
+use crate::config;
+
+pub const SOURCE_KEEPER_USERNAME: &str = "Source Keeper";
+pub const INVADER_USERNAME: &str = "Invader";
+
+// The amount of mineral and energy a lab consumes to boost a single body part
+pub const LAB_BOOST_MINERAL: u32 = 30;
+pub const LAB_BOOST_ENERGY: u32 = 20;
+
 pub struct SourceInfo {
     pub non_wall_terrain_count: u32,
     pub nearby_creep_count: u32,
     pub nearby_source_harvester_count: u32,
     pub has_link: bool,
+    pub has_container: bool,
 }
 
 impl SourceInfo {
     pub fn new(source: &Source, ignore_creep: Option<&Creep>) -> SourceInfo {
+        // clamped so sources on the room edge don't underflow
+        let x = source.pos().x().u8();
+        let y = source.pos().y().u8();
+        let (top, left) = (y.saturating_sub(1), x.saturating_sub(1));
+        let (bottom, right) = (std::cmp::min(y + 1, 49), std::cmp::min(x + 1, 49));
+
         let non_wall_terrain_count = source
             .room()
             .unwrap()
-            .look_at_area(
-                source.pos().y().u8() - 1,
-                source.pos().x().u8() - 1,
-                source.pos().y().u8() + 1,
-                source.pos().x().u8() + 1,
-            )
+            .look_at_area(top, left, bottom, right)
             .iter()
             .filter(|o| {
                 if let LookResult::Terrain(terrain) = o.look_result {
@@ -33,12 +51,7 @@ impl SourceInfo {
         let nearby_creep_count = source
             .room()
             .unwrap()
-            .look_at_area(
-                source.pos().y().u8() - 1,
-                source.pos().x().u8() - 1,
-                source.pos().y().u8() + 1,
-                source.pos().x().u8() + 1,
-            )
+            .look_at_area(top, left, bottom, right)
             .iter()
             .filter(|o| {
                 if let LookResult::Creep(creep) = &o.look_result {
@@ -56,12 +69,7 @@ impl SourceInfo {
         let nearby_source_harvester_count = source
             .room()
             .unwrap()
-            .look_at_area(
-                source.pos().y().u8() - 1,
-                source.pos().x().u8() - 1,
-                source.pos().y().u8() + 1,
-                source.pos().x().u8() + 1,
-            )
+            .look_at_area(top, left, bottom, right)
             .iter()
             .filter(|o| {
                 if let LookResult::Creep(creep) = &o.look_result {
@@ -80,32 +88,28 @@ impl SourceInfo {
             })
             .count() as u32;
 
-        let has_link = source
+        let has_link = super::utils::get_source_links(&source.room().unwrap())
+            .iter()
+            .any(|(_, source_id)| *source_id == source.id());
+
+        let has_container = source
             .room()
             .unwrap()
-            .look_at_area(
-                source.pos().y().u8() - 2,
-                source.pos().x().u8() - 2,
-                source.pos().y().u8() + 2,
-                source.pos().x().u8() + 2,
-            )
+            .look_at_area(top, left, bottom, right)
             .iter()
-            .filter(|o| {
+            .any(|o| {
                 if let LookResult::Structure(structure) = &o.look_result {
-                    if structure.structure_type() == screeps::StructureType::Link {
-                        return true;
-                    }
+                    return structure.structure_type() == screeps::StructureType::Container;
                 }
                 false
-            })
-            .count()
-            > 0;
+            });
 
         SourceInfo {
             non_wall_terrain_count,
             nearby_creep_count,
             nearby_source_harvester_count,
             has_link,
+            has_container,
         }
     }
 }
@@ -115,12 +119,51 @@ pub struct RoomInfo {
     pub sources: Vec<SourceInfo>,
     pub structures: Vec<StructureObject>,
     pub my_structures: Vec<StructureObject>,
+    pub invader_cores: Vec<StructureObject>,
+    pub keeper_lairs: Vec<StructureKeeperLair>,
     pub my_spawns: Vec<StructureSpawn>,
     pub construction_sites: Vec<ConstructionSite>,
+    pub hostile_creeps: Vec<Creep>,
+    pub source_keepers: Vec<Creep>,
+    /// Threat from other players' creeps. Source keepers and invaders aren't counted.
+    pub threat_level: u32,
+    pub invader_threat_level: u32,
     pub controller: Option<StructureController>,
     pub links: LinkTypeMap,
 }
 
+/// Subsystems that only make sense once the room's controller is high enough to build them
+#[derive(Clone, Copy, Debug)]
+pub enum Feature {
+    SourceRoads,
+    Storage,
+    Links,
+    Terminal,
+    Labs,
+}
+
+impl Feature {
+    pub fn min_controller_level(&self) -> u8 {
+        match self {
+            Feature::SourceRoads => 3,
+            Feature::Storage => 4,
+            Feature::Links => 5,
+            Feature::Terminal => 6,
+            Feature::Labs => 6,
+        }
+    }
+}
+
+/// How far along an owned room is. Bootstrapping rooms have every creep harvesting and upgrading
+/// to RCL2, growing rooms run on workers and containers, and mature rooms have a storage to run a
+/// link and hauler economy around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoomPhase {
+    Bootstrap,
+    Growth,
+    Mature,
+}
+
 impl RoomInfo {
     pub fn new(room: Room) -> RoomInfo {
         let sources = room
@@ -133,10 +176,45 @@ impl RoomInfo {
 
         let my_structures = room.find(screeps::constants::find::MY_STRUCTURES, None);
 
+        let invader_cores = structures
+            .iter()
+            .filter(|s| matches!(s, StructureObject::StructureInvaderCore(_)))
+            .cloned()
+            .collect();
+
+        let keeper_lairs = structures
+            .iter()
+            .filter_map(|s| {
+                if let StructureObject::StructureKeeperLair(lair) = s {
+                    return Some(lair.clone());
+                }
+                None
+            })
+            .collect();
+
         let my_spawns = room.find(screeps::constants::find::MY_SPAWNS, None);
 
         let construction_sites = room.find(screeps::constants::find::CONSTRUCTION_SITES, None);
 
+        let hostile_creeps = room.find(screeps::constants::find::HOSTILE_CREEPS, None);
+
+        let source_keepers = hostile_creeps
+            .iter()
+            .filter(|c| get_hostile_owner(c) == HostileOwner::SourceKeeper)
+            .cloned()
+            .collect();
+
+        let threat_level = hostile_creeps
+            .iter()
+            .filter(|c| get_hostile_owner(c) == HostileOwner::Player)
+            .map(get_threat_level)
+            .sum();
+        let invader_threat_level = hostile_creeps
+            .iter()
+            .filter(|c| get_hostile_owner(c) == HostileOwner::Invader)
+            .map(get_threat_level)
+            .sum();
+
         let controller = room.controller();
 
         let links = LinkTypeMap::new(&room);
@@ -146,12 +224,283 @@ impl RoomInfo {
             sources,
             structures,
             my_structures,
+            invader_cores,
+            keeper_lairs,
             my_spawns,
             construction_sites,
+            hostile_creeps,
+            source_keepers,
+            threat_level,
+            invader_threat_level,
             controller,
             links,
         }
     }
+
+    /// Returns true if a source keeper is close to the position, or about to spawn nearby
+    pub fn is_guarded_by_keeper(&self, pos: Position) -> bool {
+        let keeper_nearby = self
+            .source_keepers
+            .iter()
+            .any(|k| k.pos().in_range_to(pos, config::KEEPER_DANGER_RANGE));
+
+        let keeper_spawning_nearby = self.keeper_lairs.iter().any(|lair| {
+            lair.pos().in_range_to(pos, config::KEEPER_DANGER_RANGE)
+                && lair
+                    .ticks_to_spawn()
+                    .map(|ticks| ticks <= config::KEEPER_SPAWN_WARNING_TICKS)
+                    .unwrap_or(false)
+        });
+
+        keeper_nearby || keeper_spawning_nearby
+    }
+
+    /// Returns the containers close enough to the controller for upgraders to work from
+    pub fn get_controller_containers(&self) -> Vec<&StructureContainer> {
+        let controller = match self.controller.as_ref() {
+            Some(controller) => controller,
+            None => return Vec::new(),
+        };
+
+        self.structures
+            .iter()
+            .filter_map(|s| {
+                if let StructureObject::StructureContainer(container) = s {
+                    if container
+                        .pos()
+                        .in_range_to(controller.pos(), config::CONTROLLER_CONTAINER_RANGE)
+                    {
+                        return Some(container);
+                    }
+                }
+                None
+            })
+            .collect()
+    }
+
+    /// Returns the containers next to the room's mineral that its extractor output is dropped into
+    pub fn get_mineral_containers(&self) -> Vec<&StructureContainer> {
+        let minerals = self.room.find(find::MINERALS, None);
+
+        self.structures
+            .iter()
+            .filter_map(|s| {
+                if let StructureObject::StructureContainer(container) = s {
+                    if minerals.iter().any(|m| container.pos().is_near_to(m.pos())) {
+                        return Some(container);
+                    }
+                }
+                None
+            })
+            .collect()
+    }
+
+    /// Returns the room's mineral if we have an extractor on it, whether or not it's depleted
+    pub fn get_extracted_mineral(&self) -> Option<Mineral> {
+        self.room.find(find::MINERALS, None).into_iter().find(|m| {
+            self.my_structures
+                .iter()
+                .any(|s| s.structure_type() == StructureType::Extractor && s.pos() == m.pos())
+        })
+    }
+
+    /// Returns our ramparts within `RAMPART_DEFENCE_RANGE` of a hostile creep. These are the choke
+    /// points rampart defenders hold while the room is under attack.
+    pub fn get_threatened_ramparts(&self) -> Vec<&StructureRampart> {
+        self.my_structures
+            .iter()
+            .filter_map(|s| {
+                if let StructureObject::StructureRampart(rampart) = s {
+                    if self.hostile_creeps.iter().any(|c| {
+                        c.pos()
+                            .in_range_to(rampart.pos(), config::RAMPART_DEFENCE_RANGE)
+                    }) {
+                        return Some(rampart);
+                    }
+                }
+                None
+            })
+            .collect()
+    }
+
+    /// Returns the build progress still needed to finish our construction sites in the room
+    pub fn get_remaining_construction(&self) -> u32 {
+        self.construction_sites
+            .iter()
+            .filter(|site| site.my())
+            .map(|site| site.progress_total() - site.progress())
+            .sum()
+    }
+
+    pub fn phase(&self) -> RoomPhase {
+        let level = self
+            .controller
+            .as_ref()
+            .filter(|c| c.my())
+            .map(|c| c.level())
+            .unwrap_or(0);
+        if level < 2 {
+            return RoomPhase::Bootstrap;
+        }
+
+        let has_storage = self.is_feature_enabled(Feature::Storage)
+            && self
+                .my_structures
+                .iter()
+                .any(|s| matches!(s, StructureObject::StructureStorage(_)));
+        if has_storage {
+            RoomPhase::Mature
+        } else {
+            RoomPhase::Growth
+        }
+    }
+
+    /// Returns true if the room is ours and its controller is high enough to use the feature
+    pub fn is_feature_enabled(&self, feature: Feature) -> bool {
+        self.controller
+            .as_ref()
+            .map(|c| c.my() && c.level() >= feature.min_controller_level())
+            .unwrap_or(false)
+    }
+
+    /// Returns how many times over the configured boost for the part multiplies it, if a lab in the
+    /// room is stocked to boost this many parts. Returns 1 when there's nothing to boost with.
+    pub fn get_stocked_boost_multiplier(&self, part: Part, part_count: u32) -> u32 {
+        if !self.is_feature_enabled(Feature::Labs) {
+            return 1;
+        }
+
+        let boost = config::COMBAT_BOOSTS.iter().find(|(p, _)| *p == part);
+        if boost.is_none() {
+            return 1;
+        }
+        let (_, resource) = *boost.unwrap();
+
+        let stocked = self.my_structures.iter().any(|s| {
+            if let StructureObject::StructureLab(lab) = s {
+                return lab.mineral_type() == Some(resource)
+                    && lab.store().get_used_capacity(Some(resource))
+                        >= part_count * LAB_BOOST_MINERAL
+                    && lab.store().get_used_capacity(Some(ResourceType::Energy))
+                        >= part_count * LAB_BOOST_ENERGY;
+            }
+            false
+        });
+
+        if stocked {
+            get_boost_multiplier(resource)
+        } else {
+            1
+        }
+    }
+}
+
+/// Returns how much of each resource the room has in stock across its storage, terminal and
+/// containers
+pub fn room_resource_totals(room_info: &RoomInfo) -> HashMap<ResourceType, u32> {
+    let mut totals: HashMap<ResourceType, u32> = HashMap::new();
+    for structure in room_info.structures.iter() {
+        let store = match structure {
+            StructureObject::StructureStorage(storage) => storage.store(),
+            StructureObject::StructureTerminal(terminal) => terminal.store(),
+            StructureObject::StructureContainer(container) => container.store(),
+            _ => continue,
+        };
+
+        for resource in store.store_types() {
+            *totals.entry(resource).or_insert(0) += store.get_used_capacity(Some(resource));
+        }
+    }
+    totals
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HostileOwner {
+    Player,
+    Invader,
+    SourceKeeper,
+}
+
+/// Returns whether a hostile creep belongs to another player or to one of the NPCs. Source keepers
+/// never leave their lair and invaders are short-lived, so neither is worth a full defence.
+pub fn get_hostile_owner(creep: &Creep) -> HostileOwner {
+    let username = creep.owner().username();
+    if username == SOURCE_KEEPER_USERNAME {
+        HostileOwner::SourceKeeper
+    } else if username == INVADER_USERNAME {
+        HostileOwner::Invader
+    } else {
+        HostileOwner::Player
+    }
+}
+
+/// Returns how dangerous a creep is, based on its active attack, ranged attack and heal parts
+pub fn get_threat_level(creep: &Creep) -> u32 {
+    creep
+        .body()
+        .iter()
+        .filter(|p| {
+            p.hits() > 0 && matches!(p.part(), Part::Attack | Part::RangedAttack | Part::Heal)
+        })
+        .count() as u32
+}
+
+/// Returns the creep's active heal parts. Healers keep everything around them alive, so they're
+/// killed first.
+pub fn get_heal_part_count(creep: &Creep) -> u32 {
+    creep
+        .body()
+        .iter()
+        .filter(|p| p.hits() > 0 && p.part() == Part::Heal)
+        .count() as u32
+}
+
+/// Returns true if the road, container or rampart will be destroyed by its next decay and that
+/// decay is due within `DECAY_REPAIR_TICKS`
+pub fn is_about_to_decay(structure: &StructureObject) -> bool {
+    let (ticks_to_decay, decay_amount) = match structure {
+        // roads on swamps and walls have more hits and lose proportionally more when they decay
+        StructureObject::StructureRoad(road) => (
+            road.ticks_to_decay(),
+            ROAD_DECAY_AMOUNT * road.hits_max() / ROAD_HITS,
+        ),
+        StructureObject::StructureContainer(container) => {
+            (container.ticks_to_decay(), CONTAINER_DECAY)
+        }
+        StructureObject::StructureRampart(rampart) => {
+            (rampart.ticks_to_decay(), RAMPART_DECAY_AMOUNT)
+        }
+        _ => return false,
+    };
+
+    ticks_to_decay <= config::DECAY_REPAIR_TICKS && structure.as_structure().hits() <= decay_amount
+}
+
+/// Returns the creep's active attack parts, with boosted parts counted as many times over as the
+/// boost multiplies their damage
+pub fn get_attack_power(creep: &Creep) -> u32 {
+    creep
+        .body()
+        .iter()
+        .filter(|p| p.hits() > 0 && p.part() == Part::Attack)
+        .map(|p| p.boost().map(get_boost_multiplier).unwrap_or(1))
+        .sum()
+}
+
+/// Returns how many times over a boost multiplies the effect of the part it's applied to
+pub fn get_boost_multiplier(boost: ResourceType) -> u32 {
+    match boost {
+        ResourceType::UtriumHydride => 2,
+        ResourceType::UtriumAcid => 3,
+        ResourceType::CatalyzedUtriumAcid => 4,
+        ResourceType::KeaniumOxide => 2,
+        ResourceType::KeaniumAlkalide => 3,
+        ResourceType::CatalyzedKeaniumAlkalide => 4,
+        ResourceType::LemergiumOxide => 2,
+        ResourceType::LemergiumAlkalide => 3,
+        ResourceType::CatalyzedLemergiumAlkalide => 4,
+        _ => 1,
+    }
 }
 
 #[derive(Default)]
@@ -166,7 +515,7 @@ pub struct SourceLink(pub StructureLink, pub Source);
 pub struct StorageLink(pub StructureLink, pub StructureStorage);
 pub struct ControllerLink(pub StructureLink, pub StructureController);
 
-pub struct UnknownLink(StructureLink);
+pub struct UnknownLink(pub StructureLink);
 
 impl LinkTypeMap {
     pub fn new(room: &Room) -> Self {
@@ -194,27 +543,46 @@ impl LinkTypeMap {
             .collect::<Vec<StructureStorage>>();
 
         if let Some(controller) = room.controller() {
-            'link_loop: for link in links {
-                for source in sources.iter() {
-                    if link.pos().in_range_to(source.pos(), 2) {
+            for link in links {
+                // a link close to more than one kind of structure is given to the closest one.
+                // Ties go to the source, then the controller, then storage.
+                let source = sources
+                    .iter()
+                    .filter(|s| link.pos().in_range_to(s.pos(), config::SOURCE_LINK_RANGE))
+                    .min_by_key(|s| link.pos().get_range_to(s.pos()));
+                let controller_in_range = link.pos().in_range_to(controller.pos(), 2);
+                let storage = storages
+                    .iter()
+                    .filter(|s| link.pos().in_range_to(s.pos(), 2))
+                    .min_by_key(|s| link.pos().get_range_to(s.pos()));
+
+                let candidates = [
+                    source.map(|s| link.pos().get_range_to(s.pos())),
+                    controller_in_range.then(|| link.pos().get_range_to(controller.pos())),
+                    storage.map(|s| link.pos().get_range_to(s.pos())),
+                ];
+                let closest = candidates
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, range)| range.map(|range| (range, i)))
+                    .min();
+                match closest {
+                    Some((_, 0)) => {
                         map.source_links
-                            .push(SourceLink(link.clone(), source.clone()));
-                        continue 'link_loop;
+                            .push(SourceLink(link.clone(), source.unwrap().clone()));
+                        continue;
                     }
-                }
-
-                if link.pos().in_range_to(controller.pos(), 2) {
-                    map.controller_links
-                        .push(ControllerLink(link.clone(), controller.clone()));
-                    continue;
-                }
-
-                for storage in storages.iter() {
-                    if link.pos().in_range_to(storage.pos(), 2) {
+                    Some((_, 1)) => {
+                        map.controller_links
+                            .push(ControllerLink(link.clone(), controller.clone()));
+                        continue;
+                    }
+                    Some((_, _)) => {
                         map.storage_links
-                            .push(StorageLink(link.clone(), storage.clone()));
-                        continue 'link_loop;
+                            .push(StorageLink(link.clone(), storage.unwrap().clone()));
+                        continue;
                     }
+                    None => {}
                 }
 
                 map.unknown_links.push(UnknownLink(link.clone()));
@@ -223,4 +591,14 @@ impl LinkTypeMap {
 
         map
     }
+
+    /// Returns the controller link upgraders work from, if the room has one
+    pub fn first_controller_link(&self) -> Option<&ControllerLink> {
+        self.controller_links.first()
+    }
+
+    /// Returns the storage link storagers empty, if the room has one
+    pub fn first_storage_link(&self) -> Option<&StorageLink> {
+        self.storage_links.first()
+    }
 }
@@ -1,7 +1,15 @@
+use std::collections::HashSet;
+
 use log::*;
 use screeps::{
-    pathfinder::{MultiRoomCostResult, SingleRoomCostResult},
-    Creep, ErrorCode, FindPathOptions, HasPosition, Path, SharedCreepProperties,
+    game::{
+        self,
+        map::{self, FindRouteOptions},
+    },
+    look::LookResult,
+    pathfinder::{self, MultiRoomCostResult, SearchOptions, SingleRoomCostResult},
+    Creep, Direction, ErrorCode, FindPathOptions, HasPosition, Path, PolyStyle, Position, RoomName,
+    RoomPosition, RoomVisual, SharedCreepProperties, StructureProperties, StructureType, Terrain,
 };
 use wasm_bindgen::JsValue;
 
@@ -22,7 +30,7 @@ pub trait MovesAlongCachedPath: Stuckable {
     }
 
     fn recalculate_path<T: HasPosition>(&mut self, creep: &Creep, target: T, ignore_creeps: bool) {
-        if creep.room().unwrap().name() == target.pos().room_name() {
+        if creep.room().map(|room| room.name()) == Some(target.pos().room_name()) {
             let options: FindPathOptions<_, MultiRoomCostResult> =
                 FindPathOptions::new().ignore_creeps(ignore_creeps);
             let path = creep.pos().find_path_to(&target.pos(), Some(options));
@@ -36,27 +44,50 @@ pub trait MovesAlongCachedPath: Stuckable {
     }
 
     fn move_along_cached_path(&mut self, creep: &Creep) -> Result<(), ErrorCode> {
+        if crate::config::DEBUG_VISUALS {
+            self.draw_cached_path(creep);
+        }
+
         if let Some(path) = self.get_cached_path() {
             let result: Result<(), ErrorCode> =
                 creep.move_by_path(&JsValue::from_str(&path.to_string()));
 
-            result.unwrap_or_else(|e| match e {
-                ErrorCode::Tired => {
+            match result {
+                Ok(()) => self.set_stuck_count(0),
+                Err(ErrorCode::Tired) => {
                     debug!("{} too tired to move along cached path", creep.name());
                 }
-                _ => {
+                Err(e) => {
                     debug!("{} unable to move along cached path: {:?}", creep.name(), e);
                     self.set_stuck_count(self.get_stuck_count() + 1);
+
+                    // the path was probably blocked by something built since it was calculated
+                    if self.get_stuck_count() >= crate::config::PATH_FAILURE_LIMIT {
+                        debug!("{} discarding its cached path", creep.name());
+                        self.empty_cached_path();
+                    }
                 }
-            });
+            }
 
-            self.set_stuck_count(0);
             return result;
         } else {
             debug!("no cached path to move along");
         }
         Ok(())
     }
+
+    /// Draws the cached path as a line starting at the creep
+    fn draw_cached_path(&self, creep: &Creep) {
+        if let Some(Path::Vectorized(steps)) = self.get_cached_path() {
+            let mut points = vec![(creep.pos().x().u8() as f32, creep.pos().y().u8() as f32)];
+            points.extend(steps.iter().map(|step| (step.x as f32, step.y as f32)));
+
+            RoomVisual::new(Some(creep.pos().room_name())).poly(
+                points,
+                Some(PolyStyle::default().stroke("#ffffff").opacity(0.3)),
+            );
+        }
+    }
 }
 
 pub trait Stuckable {
@@ -64,3 +95,253 @@ pub trait Stuckable {
     fn get_stuck_count(&self) -> u32;
     fn set_stuck_count(&mut self, count: u32);
 }
+
+/// Moves the creep towards a target that may be in another room, routing around rooms flagged
+/// hostile in the room intel. The target's own room is never avoided, so creeps can still be sent
+/// into a hostile room on purpose.
+pub fn travel_to<T: HasPosition>(creep: &Creep, target: T) -> Result<(), ErrorCode> {
+    let target_pos = target.pos();
+    let current_room = creep.pos().room_name();
+    let target_room = target_pos.room_name();
+    if current_room == target_room {
+        return creep.move_to(target_pos);
+    }
+
+    match get_next_room(current_room, target_room) {
+        Some(next_room) if next_room != target_room => {
+            creep.move_to(RoomPosition::new(25, 25, next_room))
+        }
+        _ => creep.move_to(target_pos),
+    }
+}
+
+/// Returns true if every tile next to the position that a creep could stand on already has a creep
+/// on it
+pub fn is_surrounded(pos: Position) -> bool {
+    let room = game::rooms().get(pos.room_name());
+    if room.is_none() {
+        return false;
+    }
+    let room = room.unwrap();
+
+    // clamped so positions on the room edge don't underflow
+    let x = pos.x().u8();
+    let y = pos.y().u8();
+    let area = room.look_at_area(
+        y.saturating_sub(1),
+        x.saturating_sub(1),
+        std::cmp::min(y + 1, 49),
+        std::cmp::min(x + 1, 49),
+    );
+
+    let mut tiles = HashSet::new();
+    let mut blocked_tiles = HashSet::new();
+    blocked_tiles.insert((x, y));
+    for o in area.iter() {
+        let tile = (o.x, o.y);
+        tiles.insert(tile);
+        match &o.look_result {
+            LookResult::Terrain(Terrain::Wall) | LookResult::Creep(_) => {
+                blocked_tiles.insert(tile);
+            }
+            LookResult::Structure(structure)
+                if !matches!(
+                    structure.structure_type(),
+                    StructureType::Road | StructureType::Container | StructureType::Rampart
+                ) =>
+            {
+                blocked_tiles.insert(tile);
+            }
+            _ => {}
+        }
+    }
+
+    tiles.is_subset(&blocked_tiles)
+}
+
+/// What `approach_then` had the creep do this tick
+#[derive(Debug, PartialEq)]
+pub enum Approach {
+    Acted,
+    Moved,
+}
+
+#[derive(Debug)]
+pub enum ApproachError {
+    Action(ErrorCode),
+    Move(ErrorCode),
+}
+
+/// Runs the action once the creep is within range of the target, otherwise moves the creep towards
+/// it. An action that fails with `NotInRange` moves the creep instead, and a creep too tired to
+/// move is treated as having moved.
+pub fn approach_then<F>(
+    creep: &Creep,
+    target: Position,
+    range: u32,
+    action: F,
+) -> Result<Approach, ApproachError>
+where
+    F: FnOnce() -> Result<(), ErrorCode>,
+{
+    approach_with(creep, target, range, action, || travel_to(creep, target))
+}
+
+/// Same as `approach_then`, but the creep moves along the cached route from `from` (see
+/// `move_along_route`)
+pub fn approach_along_route_then<F>(
+    creep: &Creep,
+    from: Position,
+    target: Position,
+    range: u32,
+    action: F,
+) -> Result<Approach, ApproachError>
+where
+    F: FnOnce() -> Result<(), ErrorCode>,
+{
+    approach_with(creep, target, range, action, || {
+        move_along_route(creep, from, target)
+    })
+}
+
+fn approach_with<F, M>(
+    creep: &Creep,
+    target: Position,
+    range: u32,
+    action: F,
+    move_creep: M,
+) -> Result<Approach, ApproachError>
+where
+    F: FnOnce() -> Result<(), ErrorCode>,
+    M: FnOnce() -> Result<(), ErrorCode>,
+{
+    if creep.pos().in_range_to(target, range) {
+        match action() {
+            Ok(()) => return Ok(Approach::Acted),
+            Err(ErrorCode::NotInRange) => {}
+            Err(e) => return Err(ApproachError::Action(e)),
+        }
+    }
+
+    match move_creep() {
+        Ok(()) | Err(ErrorCode::Tired) => Ok(Approach::Moved),
+        Err(e) => Err(ApproachError::Move(e)),
+    }
+}
+
+/// Returns the next room on the way from one room to another, avoiding hostile rooms
+fn get_next_room(from: RoomName, to: RoomName) -> Option<RoomName> {
+    let options = FindRouteOptions::new().room_callback(|room_name: RoomName, _from: RoomName| {
+        if room_name != to && crate::intel::is_hostile(room_name) {
+            f64::INFINITY
+        } else {
+            1.0
+        }
+    });
+
+    match map::find_route(from, to, Some(options)) {
+        Ok(route) => route.first().map(|step| step.room),
+        Err(e) => {
+            debug!("no route from {} to {}: {:?}", from, to, e);
+            None
+        }
+    }
+}
+
+/// Moves the creep along a route between two fixed positions, such as a hauler going back and
+/// forth between a container and storage. The route is calculated once and shared by every creep
+/// travelling between the two positions in either direction. A creep stuck on it steps around the
+/// creeps in the way, while the shared route stays as it is for everyone else.
+pub fn move_along_route(creep: &Creep, from: Position, to: Position) -> Result<(), ErrorCode> {
+    // routes are stored from the lower to the higher packed position so both directions share one
+    let forward = from.packed_repr() <= to.packed_repr();
+    let key = if forward { (from, to) } else { (to, from) };
+
+    let route = super::ROUTE_CACHE.with(|cache| cache.borrow().get(&key).cloned());
+    let route = match route {
+        Some(route) => route,
+        None => {
+            let result = pathfinder::search(key.0, key.1, 1, Some(SearchOptions::default()));
+            if result.incomplete() {
+                debug!("no complete route from {} to {}", key.0, key.1);
+                return creep.move_to(to);
+            }
+
+            let mut route = vec![key.0];
+            route.append(&mut result.path());
+            super::ROUTE_CACHE.with(|cache| cache.borrow_mut().insert(key, route.clone()));
+            route
+        }
+    };
+
+    if is_stuck_on_route(creep) {
+        debug!(
+            "{} is stuck, stepping around the creeps in the way",
+            creep.name()
+        );
+        return match find_step_around_creeps(creep, to) {
+            Some(direction) => creep.move_direction(direction),
+            None => creep.move_to(to),
+        };
+    }
+
+    let idx = route.iter().position(|p| *p == creep.pos());
+    match idx {
+        Some(idx) => {
+            let next = if forward {
+                route.get(idx + 1)
+            } else {
+                idx.checked_sub(1).and_then(|i| route.get(i))
+            };
+
+            match next.and_then(|next| creep.pos().get_direction_to(*next)) {
+                Some(direction) => creep.move_direction(direction),
+                // the end of the route is within range of the target
+                None => creep.move_to(to),
+            }
+        }
+        None => {
+            // walk onto the route first
+            let closest = route
+                .iter()
+                .min_by_key(|p| creep.pos().get_range_to(**p))
+                .copied()
+                .unwrap_or(to);
+            creep.move_to(closest)
+        }
+    }
+}
+
+/// Returns the direction of the first step from the creep towards the target that treats creeps as
+/// obstacles, so a creep stuck behind others on a shared route can walk around them
+fn find_step_around_creeps(creep: &Creep, to: Position) -> Option<Direction> {
+    if creep.pos().room_name() != to.room_name() {
+        return None;
+    }
+
+    let options: FindPathOptions<_, SingleRoomCostResult> =
+        FindPathOptions::new().ignore_creeps(false).range(1);
+    match creep.pos().find_path_to(&to, Some(options)) {
+        Path::Vectorized(steps) => steps.first().map(|step| step.direction),
+        Path::Serialized(_) => None,
+    }
+}
+
+fn is_stuck_on_route(creep: &Creep) -> bool {
+    super::ROUTE_STUCK_COUNTS.with(|stuck_counts| {
+        let mut stuck_counts = stuck_counts.borrow_mut();
+        let entry = stuck_counts.entry(creep.name()).or_insert((creep.pos(), 0));
+
+        if entry.0 == creep.pos() && creep.fatigue() == 0 {
+            entry.1 += 1;
+        } else {
+            *entry = (creep.pos(), 0);
+        }
+
+        if entry.1 >= crate::config::ROUTE_STUCK_LIMIT {
+            entry.1 = 0;
+            return true;
+        }
+        false
+    })
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use log::*;
+use screeps::{
+    find, game, pathfinder::MultiRoomCostResult, FindPathOptions, HasPosition, MaybeHasTypedId,
+    Path, StructureProperties, StructureType, MAX_CONSTRUCTION_SITES,
+};
+
+use crate::config;
+use crate::metadata::{Feature, RoomInfo};
+
+/// Returns true on the ticks `plan_room` places construction sites, every `PLANNER_INTERVAL` ticks
+pub fn is_planning_tick() -> bool {
+    game::time() % config::PLANNER_INTERVAL == 0
+}
+
+/// Returns how many more construction sites can be placed before reaching the global limit. The
+/// game only counts new sites at the end of the tick, so this should be read once and then passed
+/// to each `plan_room` call. Counting the sites isn't free, so only call it on planning ticks.
+pub fn get_construction_site_budget() -> u32 {
+    let placed = game::construction_sites().entries().count() as u32;
+    MAX_CONSTRUCTION_SITES.saturating_sub(placed)
+}
+
+/// Places construction sites for the room's planned structures. Only runs every
+/// `PLANNER_INTERVAL` ticks since pathing to every source is expensive.
+pub fn plan_room(room_info: &RoomInfo, construction_site_budget: &mut u32) {
+    if !is_planning_tick() {
+        return;
+    }
+
+    remove_stuck_construction_sites(room_info);
+
+    let mut planned_sites = vec![];
+    if room_info.is_feature_enabled(Feature::SourceRoads) {
+        planned_sites.append(&mut plan_source_roads(room_info));
+    }
+
+    place_construction_sites(room_info, planned_sites, construction_site_budget);
+}
+
+/// How much a structure is worth building, highest first. Once the global construction site
+/// limit is close, only structures above `LOW_VALUE_STRUCTURE` are placed.
+fn get_structure_value(structure_type: StructureType) -> u32 {
+    match structure_type {
+        StructureType::Spawn => 10,
+        StructureType::Extension => 9,
+        StructureType::Tower => 8,
+        StructureType::Storage => 7,
+        StructureType::Link | StructureType::Terminal => 6,
+        StructureType::Container => 5,
+        StructureType::Extractor | StructureType::Lab => 4,
+        StructureType::Road => LOW_VALUE_STRUCTURE,
+        StructureType::Rampart | StructureType::Wall => 1,
+        _ => 3,
+    }
+}
+
+const LOW_VALUE_STRUCTURE: u32 = 2;
+
+/// Places the planned sites, most valuable first, without going over the global construction site
+/// limit. Low value sites are held back while fewer than `CONSTRUCTION_SITE_RESERVE` sites are
+/// left, so there's room for anything more important.
+fn place_construction_sites(
+    room_info: &RoomInfo,
+    mut planned_sites: Vec<(u8, u8, StructureType)>,
+    construction_site_budget: &mut u32,
+) {
+    planned_sites.sort_by_key(|(_, _, structure_type)| {
+        std::cmp::Reverse(get_structure_value(*structure_type))
+    });
+
+    for (x, y, structure_type) in planned_sites {
+        if *construction_site_budget == 0 {
+            debug!("construction site limit reached");
+            return;
+        }
+        if *construction_site_budget <= config::CONSTRUCTION_SITE_RESERVE
+            && get_structure_value(structure_type) <= LOW_VALUE_STRUCTURE
+        {
+            continue;
+        }
+
+        // placing a site where the structure already exists just returns an error
+        match room_info
+            .room
+            .create_construction_site(x, y, structure_type, None)
+        {
+            Ok(()) => *construction_site_budget -= 1,
+            Err(e) => debug!(
+                "couldn't place {:?} at ({}, {}): {:?}",
+                structure_type, x, y, e
+            ),
+        }
+    }
+}
+
+/// Removes construction sites that builders can't finish: sites for structures the controller
+/// level no longer allows, and sites that haven't progressed in `STUCK_CONSTRUCTION_SITE_TICKS`
+fn remove_stuck_construction_sites(room_info: &RoomInfo) {
+    let controller_level = match room_info.controller.as_ref() {
+        Some(controller) if controller.my() => controller.level(),
+        _ => return,
+    };
+
+    let mut built_counts: HashMap<StructureType, u32> = HashMap::new();
+    for structure in room_info.structures.iter() {
+        *built_counts.entry(structure.structure_type()).or_insert(0) += 1;
+    }
+
+    // forget sites that were finished or removed
+    super::CONSTRUCTION_SITE_PROGRESS.with(|progress| {
+        progress
+            .borrow_mut()
+            .retain(|site_id, _| site_id.resolve().is_some());
+    });
+
+    let time = game::time();
+    for site in room_info.construction_sites.iter() {
+        if !site.my() {
+            continue;
+        }
+
+        let structure_type = site.structure_type();
+        let allowed = structure_type.controller_structures(controller_level as u32);
+        let over_limit = *built_counts.get(&structure_type).unwrap_or(&0) >= allowed;
+
+        let site_id = match site.try_id() {
+            Some(site_id) => site_id,
+            None => continue,
+        };
+        let stuck = super::CONSTRUCTION_SITE_PROGRESS.with(|progress| {
+            let mut progress = progress.borrow_mut();
+            let entry = progress.entry(site_id).or_insert((site.progress(), time));
+            if entry.0 != site.progress() {
+                *entry = (site.progress(), time);
+            }
+            time - entry.1 >= config::STUCK_CONSTRUCTION_SITE_TICKS
+        });
+
+        if over_limit || stuck {
+            info!(
+                "removing {:?} construction site at {} (over limit: {}, stuck: {})",
+                structure_type,
+                site.pos(),
+                over_limit,
+                stuck
+            );
+            site.remove().unwrap_or_else(|e| {
+                info!("couldn't remove construction site: {:?}", e);
+            });
+            super::CONSTRUCTION_SITE_PROGRESS.with(|progress| {
+                progress.borrow_mut().remove(&site_id);
+            });
+        }
+    }
+}
+
+/// Plans roads along the path from the first spawn to each source so harvesters and haulers
+/// don't crawl through swamps
+fn plan_source_roads(room_info: &RoomInfo) -> Vec<(u8, u8, StructureType)> {
+    let mut roads = vec![];
+    let spawn = room_info.my_spawns.first();
+    if spawn.is_none() {
+        return roads;
+    }
+    let spawn = spawn.unwrap();
+
+    for source in room_info.room.find(find::SOURCES, None) {
+        let options: FindPathOptions<_, MultiRoomCostResult> =
+            FindPathOptions::new().ignore_creeps(true).range(1);
+        let path = spawn.pos().find_path_to(&source.pos(), Some(options));
+
+        let steps = match path {
+            Path::Vectorized(steps) => steps,
+            Path::Serialized(_) => continue,
+        };
+
+        for step in steps {
+            roads.push((step.x as u8, step.y as u8, StructureType::Road));
+        }
+    }
+
+    roads
+}
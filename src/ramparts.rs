@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+
+use log::*;
+use screeps::{HasPosition, Position, StructureObject};
+
+use crate::config;
+use crate::metadata::RoomInfo;
+
+/// Sets every rampart in the room to its configured public/private state. Ramparts over roads
+/// near the room exits are public, everything else is private. All ramparts are made private
+/// while hostiles are present.
+pub fn maintain_ramparts(room_info: &RoomInfo) {
+    let under_attack = !room_info.hostile_creeps.is_empty();
+
+    let road_positions = room_info
+        .structures
+        .iter()
+        .filter_map(|s| {
+            if let StructureObject::StructureRoad(road) = s {
+                return Some(road.pos());
+            }
+            None
+        })
+        .collect::<HashSet<Position>>();
+
+    for structure in room_info.my_structures.iter() {
+        if let StructureObject::StructureRampart(rampart) = structure {
+            let pos = rampart.pos();
+            let should_be_public = !under_attack
+                && road_positions.contains(&pos)
+                && is_near_exit(pos, config::PUBLIC_RAMPART_EXIT_RANGE);
+
+            if rampart.is_public() != should_be_public {
+                rampart.set_public(should_be_public).unwrap_or_else(|e| {
+                    info!("couldn't set rampart public state: {:?}", e);
+                });
+            }
+        }
+    }
+}
+
+fn is_near_exit(pos: Position, range: u8) -> bool {
+    let x = pos.x().u8();
+    let y = pos.y().u8();
+    x <= range || y <= range || x >= 49 - range || y >= 49 - range
+}
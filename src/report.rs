@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use log::*;
+use screeps::{
+    find, game,
+    pathfinder::{self, SearchOptions},
+    HasPosition, HasStore, HasTypedId, OwnedStructureProperties, ResourceType, RoomName,
+    StructureObject, StructureProperties, StructureType,
+};
+
+use crate::config;
+use crate::metadata::{room_resource_totals, Feature, RoomInfo, SourceLink, UnknownLink};
+
+/// Logs a compact summary of the empire's resources: energy and minerals held in each owned room,
+/// then GCL, GPL and credits
+pub fn log_resource_summary(room_info_map: &HashMap<RoomName, RoomInfo>) {
+    info!("==== resource summary (tick {}) ====", game::time());
+
+    let mut room_names = room_info_map.keys().collect::<Vec<_>>();
+    room_names.sort_by_key(|room_name| room_name.to_string());
+
+    for room_name in room_names {
+        let room_info = room_info_map.get(room_name).unwrap();
+        let controller = room_info.controller.as_ref();
+        if !controller.map(|c| c.my()).unwrap_or(false) {
+            continue;
+        }
+
+        let mut storage_energy = 0;
+        let mut terminal_energy = 0;
+        for structure in room_info.my_structures.iter() {
+            match structure {
+                StructureObject::StructureStorage(storage) => {
+                    storage_energy = storage
+                        .store()
+                        .get_used_capacity(Some(ResourceType::Energy));
+                }
+                StructureObject::StructureTerminal(terminal) => {
+                    terminal_energy = terminal
+                        .store()
+                        .get_used_capacity(Some(ResourceType::Energy));
+                }
+                _ => {}
+            }
+        }
+
+        let mut minerals = room_resource_totals(room_info)
+            .iter()
+            .filter(|(resource, _)| **resource != ResourceType::Energy)
+            .map(|(resource, amount)| format!("{:?}: {}", resource, amount))
+            .collect::<Vec<_>>();
+        minerals.sort();
+
+        info!(
+            "{} [RCL {} {:?}] storage: {} | terminal: {} | minerals: [{}]",
+            room_name,
+            controller.unwrap().level(),
+            room_info.phase(),
+            storage_energy,
+            terminal_energy,
+            minerals.join(", ")
+        );
+    }
+
+    info!(
+        "GCL {} ({:.0}/{:.0}) | GPL {} | credits: {:.2}",
+        game::gcl::level(),
+        game::gcl::progress(),
+        game::gcl::progress_total(),
+        game::gpl::level(),
+        game::market::credits()
+    );
+}
+
+/// Logs which source in each owned room would gain the most from a source link, for rooms that
+/// can still build one. Haulers walk to storage and back for every load from a source without a
+/// link, so the source with the longest path to storage is the biggest bottleneck.
+pub fn log_link_recommendations(room_info_map: &HashMap<RoomName, RoomInfo>) {
+    for (room_name, room_info) in room_info_map.iter() {
+        let controller = room_info.controller.as_ref();
+        if !controller.map(|c| c.my()).unwrap_or(false)
+            || !room_info.is_feature_enabled(Feature::Links)
+        {
+            continue;
+        }
+
+        let storage = room_info.room.storage();
+        if storage.is_none() {
+            continue;
+        }
+        let storage = storage.unwrap();
+
+        let built_links = room_info
+            .my_structures
+            .iter()
+            .filter(|s| s.structure_type() == StructureType::Link)
+            .count() as u32;
+        let allowed_links =
+            StructureType::Link.controller_structures(controller.unwrap().level() as u32);
+        if built_links >= allowed_links {
+            continue;
+        }
+
+        let slowest_source = room_info
+            .room
+            .find(find::SOURCES, None)
+            .into_iter()
+            .filter(|source| {
+                !room_info
+                    .links
+                    .source_links
+                    .iter()
+                    .any(|SourceLink(_, linked_source)| linked_source.id() == source.id())
+            })
+            .filter_map(|source| {
+                let result = pathfinder::search(
+                    storage.pos(),
+                    source.pos(),
+                    1,
+                    Some(SearchOptions::default()),
+                );
+                if result.incomplete() {
+                    return None;
+                }
+                let path_length = result.path().len() as u32;
+                Some((source, path_length))
+            })
+            .max_by_key(|(_, path_length)| *path_length);
+
+        if let Some((source, path_length)) = slowest_source {
+            if path_length < config::LINK_RECOMMENDATION_MIN_PATH {
+                continue;
+            }
+
+            info!(
+                "{} could build {} more link(s). A link at the source at ({}, {}) would save haulers a {} tick round trip to storage per load",
+                room_name,
+                allowed_links - built_links,
+                source.pos().x().u8(),
+                source.pos().y().u8(),
+                path_length * 2
+            );
+        }
+    }
+}
+
+/// Logs links in owned rooms that ended up just too far from a source to be used as a source link,
+/// and links near more than one of a source, the controller and storage. Harvesters can't stand
+/// next to both a source and a link that's any further away, so they'd overflow while walking back
+/// and forth. Links near several structures are only given to the closest one.
+pub fn log_misplaced_links(room_info_map: &HashMap<RoomName, RoomInfo>) {
+    for (room_name, room_info) in room_info_map.iter() {
+        let sources = room_info.room.find(find::SOURCES, None);
+        let controller = room_info.controller.as_ref();
+        let storage = room_info.room.storage();
+
+        for structure in room_info.my_structures.iter() {
+            let link = match structure {
+                StructureObject::StructureLink(link) => link,
+                _ => continue,
+            };
+
+            let near_source = sources
+                .iter()
+                .any(|s| link.pos().in_range_to(s.pos(), config::SOURCE_LINK_RANGE));
+            let near_controller = controller.is_some_and(|c| link.pos().in_range_to(c.pos(), 2));
+            let near_storage = storage
+                .as_ref()
+                .is_some_and(|s| link.pos().in_range_to(s.pos(), 2));
+            if [near_source, near_controller, near_storage]
+                .iter()
+                .filter(|near| **near)
+                .count()
+                > 1
+            {
+                info!(
+                    "link at ({}, {}) in room {} is near more than one of a source, the controller and storage, move it so it's only near one",
+                    link.pos().x().u8(),
+                    link.pos().y().u8(),
+                    room_name,
+                );
+            }
+        }
+
+        for UnknownLink(link) in room_info.links.unknown_links.iter() {
+            if let Some(source) = sources.iter().find(|s| {
+                link.pos()
+                    .in_range_to(s.pos(), config::SOURCE_LINK_RANGE + 2)
+            }) {
+                info!(
+                    "link at ({}, {}) in room {} is {} tiles from a source, move it within {} to use it as a source link",
+                    link.pos().x().u8(),
+                    link.pos().y().u8(),
+                    room_name,
+                    link.pos().get_range_to(source.pos()),
+                    config::SOURCE_LINK_RANGE
+                );
+            }
+        }
+    }
+}
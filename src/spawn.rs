@@ -1,11 +1,17 @@
+use crate::config;
+use crate::tasks::TaskManager;
 use crate::utils::get_creep_type;
 use log::*;
-use screeps::{game, Part, ResourceType, RoomName};
-use std::collections::HashMap;
+use screeps::{
+    constants::CREEP_SPAWN_TIME, find, game, look::LookResult, Creep, HasPosition, MaybeHasTypedId,
+    Part, ResourceType, RoomName, SharedCreepProperties, StructureType, Terrain,
+};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub struct SpawnGoal {
     pub name: String,
+    pub version: u32, // bump when the role's body or behaviour changes so old creeps can be told apart
     pub body: Vec<Part>,
     pub body_upgrades: Vec<Part>,
     pub max_body_upgrades: u32,
@@ -14,6 +20,13 @@ pub struct SpawnGoal {
     pub is_global: bool,
 }
 
+/// A spawn goal's count along with the count it's changing to
+pub struct SettledCount {
+    settled: u32,
+    pending: u32,
+    pending_since: u32,
+}
+
 pub type SpawnGoals = Vec<SpawnGoal>;
 pub type RoomSpawnGoals = HashMap<RoomName, SpawnGoals>;
 pub type RoomCreepCounts = HashMap<RoomName, HashMap<String, u32>>;
@@ -24,11 +37,49 @@ pub struct SpawnManager {
 }
 
 impl SpawnManager {
-    pub fn new(room_spawn_goals: RoomSpawnGoals) -> SpawnManager {
+    pub fn new(mut room_spawn_goals: RoomSpawnGoals) -> SpawnManager {
+        // forget goals for rooms that were lost and roles that were removed
+        super::SPAWN_GOAL_COUNTS.with(|counts| {
+            counts.borrow_mut().retain(|(room_name, role), _| {
+                room_spawn_goals
+                    .get(room_name)
+                    .is_some_and(|spawn_goals| spawn_goals.iter().any(|g| &g.name == role))
+            })
+        });
+
+        let mut role_target_counts = HashMap::new();
+        for (room_name, spawn_goals) in room_spawn_goals.iter_mut() {
+            let source_count = game::rooms()
+                .get(*room_name)
+                .map(|room| room.find(find::SOURCES, None).len() as u32)
+                .unwrap_or(1);
+            for spawn_goal in spawn_goals.iter_mut() {
+                spawn_goal.count = get_settled_count(*room_name, spawn_goal);
+                role_target_counts.insert(
+                    (*room_name, spawn_goal.name.clone()),
+                    (
+                        get_target_count(spawn_goal, source_count),
+                        spawn_goal.is_global,
+                    ),
+                );
+            }
+        }
+        super::ROLE_TARGET_COUNTS.with(|counts| *counts.borrow_mut() = role_target_counts);
+
         let creeps = game::creeps();
         let room_creep_counts = creeps.values().fold(HashMap::new(), |mut acc, creep| {
             let creep_type = get_creep_type(&creep);
-            let room_name = creep.room().unwrap().name();
+            let room = creep.room();
+            if room.is_none() {
+                return acc;
+            }
+            let room_name = room.unwrap().name();
+
+            // creeps about to die no longer count so their replacement arrives in time
+            if is_due_for_replacement(&creep, &creep_type) {
+                return acc;
+            }
+
             let count: &mut HashMap<String, u32> = acc.entry(room_name).or_default();
             let creep_count = count.entry(creep_type).or_insert(0);
             *creep_count += 1;
@@ -43,6 +94,8 @@ impl SpawnManager {
 
     pub fn spawn_creeps(&mut self) {
         let mut additional = 0;
+        // rooms with a creep to spawn but not enough energy to spawn it
+        let mut energy_starved_rooms = HashSet::new();
 
         for spawn in game::spawns().values() {
             if spawn.spawning().is_some() {
@@ -50,13 +103,40 @@ impl SpawnManager {
             }
 
             let room_name = spawn.room().unwrap().name();
-
-            if (spawn.store().get_free_capacity(Some(ResourceType::Energy)) > 0)
-                && self.get_creep_count_in_room(&room_name, "worker") >= 3
+            if crate::utils::is_room_disabled(room_name)
+                || crate::utils::is_room_evacuating(room_name)
             {
                 continue;
             }
 
+            let room_creep_count: u32 = self
+                .room_creep_counts
+                .get(&room_name)
+                .map(|counts| counts.values().sum())
+                .unwrap_or(0);
+            if room_creep_count >= config::MAX_CREEPS_PER_ROOM {
+                debug!(
+                    "room {} is at its creep cap of {}",
+                    room_name,
+                    config::MAX_CREEPS_PER_ROOM
+                );
+                continue;
+            }
+
+            // Once the room has enough carriers to keep the spawn topped up, extra workers wait for
+            // the spawn to fill so they get bigger bodies. A room without carriers has no other
+            // way to get energy, so it spends whatever the spawn holds on the next worker. Other
+            // roles are never held back by this.
+            let carrier_count = self.get_creep_count_in_room(&room_name, "worker")
+                + self.get_creep_count_in_room(&room_name, "hauler");
+            let spawn_is_full = spawn.store().get_free_capacity(Some(ResourceType::Energy)) == 0;
+
+            // After a global reset or a wipe the room can't afford to wait. Workers go first and
+            // are spawned as soon as their base body is affordable, growing as the room recovers.
+            let bootstrapping = room_creep_count < config::BOOTSTRAP_CREEP_COUNT;
+            let emergency = bootstrapping || carrier_count < config::MIN_CARRIERS_BEFORE_WAITING;
+            let hold_workers = !emergency && !spawn_is_full;
+
             let source_count: u32 = spawn
                 .room()
                 .unwrap()
@@ -64,23 +144,77 @@ impl SpawnManager {
                 .len() as u32;
 
             if let Some(spawn_goals) = self.room_spawn_goals.get(&room_name) {
-                for spawn_goal in spawn_goals.iter() {
+                let mut spawn_goals = spawn_goals.iter().collect::<Vec<_>>();
+                if bootstrapping {
+                    spawn_goals.sort_by_key(|spawn_goal| spawn_goal.name != "worker");
+                }
+
+                // creeps queued with force_spawn go before everything else
+                let forced_role = get_forced_role(&room_name);
+                if let Some(role) = forced_role.as_ref() {
+                    if !spawn_goals
+                        .iter()
+                        .any(|spawn_goal| &spawn_goal.name == role)
+                    {
+                        info!("room {} has no {} spawn goal to force", room_name, role);
+                        remove_forced_role(&room_name, role);
+                    }
+                    spawn_goals.sort_by_key(|spawn_goal| &spawn_goal.name != role);
+                }
+
+                // upgraders would spend energy the room doesn't have yet, so they wait until
+                // every harvester is in place
+                let harvesters_short = spawn_goals.iter().any(|spawn_goal| {
+                    config::UPGRADER_PREREQUISITE_ROLES.contains(&spawn_goal.name.as_str())
+                        && self.get_creep_count_in_room(&room_name, &spawn_goal.name)
+                            < get_target_count(spawn_goal, source_count)
+                });
+
+                for spawn_goal in spawn_goals {
                     let creep_count = if spawn_goal.is_global {
                         self.get_global_creep_count(&spawn_goal.name)
                     } else {
                         self.get_creep_count_in_room(&room_name, &spawn_goal.name)
                     };
 
-                    let target_count = spawn_goal.count
-                        + std::cmp::max(
-                            spawn_goal.count * (source_count - 1) * spawn_goal.source_modifier,
-                            0,
-                        );
-                    if creep_count < target_count {
-                        let creep_name =
-                            format!("{}-{}-{}", spawn_goal.name, game::time(), additional);
+                    let target_count = get_target_count(spawn_goal, source_count);
+
+                    let forced = forced_role.as_ref() == Some(&spawn_goal.name);
+
+                    if spawn_goal.name == "upgrader" && harvesters_short && !forced {
+                        continue;
+                    }
+
+                    if hold_workers && spawn_goal.name == "worker" && !forced {
+                        if creep_count < target_count {
+                            energy_starved_rooms.insert(room_name);
+                        }
+                        continue;
+                    }
+
+                    if creep_count < target_count || forced {
                         let room = spawn.room().unwrap();
                         let body_cost = spawn_goal.body.iter().map(|p| p.cost()).sum::<u32>();
+                        if !is_affordable(
+                            &room_name,
+                            spawn_goal,
+                            body_cost,
+                            room.energy_capacity_available(),
+                        ) {
+                            if forced {
+                                remove_forced_role(&room_name, &spawn_goal.name);
+                            }
+                            continue;
+                        }
+
+                        let creep_name = format!(
+                            "{}-{}-v{}-{}-{}",
+                            spawn_goal.name,
+                            room_name,
+                            spawn_goal.version,
+                            game::time(),
+                            additional
+                        );
                         let additive_parts_cost = spawn_goal
                             .body_upgrades
                             .iter()
@@ -89,25 +223,36 @@ impl SpawnManager {
                             + 1;
                         let mut body_parts = spawn_goal.body.clone();
 
-                        if room.energy_available() >= body_cost {
-                            if !spawn_goal.body_upgrades.is_empty() {
-                                let remaining_energy =
-                                    std::cmp::max(room.energy_available() - body_cost, 0);
-                                let times_to_add = std::cmp::min(
-                                    remaining_energy / additive_parts_cost,
-                                    spawn_goal.max_body_upgrades,
-                                );
+                        // workers are sized by the room's energy capacity so they come out the
+                        // same size every time. A room without enough carriers to refill its
+                        // extensions makes do with whatever energy it has.
+                        let sizing_energy = if spawn_goal.name == "worker" && !emergency {
+                            room.energy_capacity_available()
+                        } else {
+                            room.energy_available()
+                        };
+                        let mut times_to_add = 0;
+                        if !spawn_goal.body_upgrades.is_empty() && sizing_energy >= body_cost {
+                            times_to_add = std::cmp::min(
+                                (sizing_energy - body_cost) / additive_parts_cost,
+                                spawn_goal.max_body_upgrades,
+                            );
+                            for _ in 0..times_to_add {
+                                for part in spawn_goal.body_upgrades.iter() {
+                                    body_parts.push(*part);
+                                }
+                            }
+                        }
+                        let total_cost = body_parts.iter().map(|p| p.cost()).sum::<u32>();
+
+                        if room.energy_available() >= total_cost {
+                            if times_to_add > 0 {
                                 info!(
                                     "Upgrading the {} creep {} times for an additional {} energy",
                                     spawn_goal.name,
                                     times_to_add,
                                     times_to_add * (additive_parts_cost - 1)
                                 );
-                                for _ in 0..times_to_add {
-                                    for part in spawn_goal.body_upgrades.iter() {
-                                        body_parts.push(*part);
-                                    }
-                                }
                             }
 
                             info!(
@@ -117,21 +262,32 @@ impl SpawnManager {
 
                             match spawn.spawn_creep(&body_parts, &creep_name) {
                                 Ok(()) => {
+                                    if forced {
+                                        remove_forced_role(&room_name, &spawn_goal.name);
+                                    }
                                     additional += 1;
                                     self.room_creep_counts
-                                        .get_mut(&room_name)
-                                        .unwrap()
+                                        .entry(room_name)
+                                        .or_default()
                                         .insert(spawn_goal.name.clone(), creep_count + 1);
                                 }
                                 Err(e) => debug!("couldn't spawn {}: {:?}", spawn_goal.name, e),
                             }
 
                             break;
+                        } else {
+                            energy_starved_rooms.insert(room_name);
+                            // save up for the forced creep instead of spending on anything else
+                            if forced {
+                                break;
+                            }
                         }
                     }
                 }
             }
         }
+
+        super::ENERGY_STARVED_ROOMS.with(|rooms| *rooms.borrow_mut() = energy_starved_rooms);
     }
 
     pub fn get_creep_count_in_room(&self, room_name: &RoomName, creep_type: &str) -> u32 {
@@ -154,3 +310,208 @@ impl SpawnManager {
         count
     }
 }
+
+/// Returns how many creeps the goal wants in a room with this many sources
+fn get_target_count(spawn_goal: &SpawnGoal, source_count: u32) -> u32 {
+    spawn_goal.count
+        + std::cmp::max(
+            spawn_goal.count * (source_count - 1) * spawn_goal.source_modifier,
+            0,
+        )
+}
+
+/// Returns how many creeps of the role the room's spawn goals want, as of the last time spawn goals
+/// were worked out. Global roles return the count wanted across every room. Roles the room has no
+/// spawn goal for want none.
+pub fn get_role_target_count(room_name: RoomName, role: &str) -> (u32, bool) {
+    super::ROLE_TARGET_COUNTS.with(|counts| {
+        counts
+            .borrow()
+            .get(&(room_name, role.to_string()))
+            .copied()
+            .unwrap_or((0, false))
+    })
+}
+
+/// Returns the role of the oldest creep queued with `force_spawn` for the room
+fn get_forced_role(room_name: &RoomName) -> Option<String> {
+    super::FORCED_SPAWNS.with(|spawns| {
+        spawns
+            .borrow()
+            .iter()
+            .find(|(forced_room, _)| forced_room == room_name)
+            .map(|(_, role)| role.clone())
+    })
+}
+
+fn remove_forced_role(room_name: &RoomName, role: &str) {
+    super::FORCED_SPAWNS.with(|spawns| {
+        let mut spawns = spawns.borrow_mut();
+        if let Some(index) = spawns
+            .iter()
+            .position(|(forced_room, forced_role)| forced_room == room_name && forced_role == role)
+        {
+            spawns.remove(index);
+        }
+    });
+}
+
+/// Returns false if the goal's base body costs more than the room can ever hold, logging it the
+/// first time so the goal can be fixed. Such goals are skipped so other roles can still spawn.
+fn is_affordable(
+    room_name: &RoomName,
+    spawn_goal: &SpawnGoal,
+    body_cost: u32,
+    energy_capacity: u32,
+) -> bool {
+    let key = (*room_name, spawn_goal.name.clone());
+    super::UNAFFORDABLE_SPAWN_GOALS.with(|goals| {
+        let mut goals = goals.borrow_mut();
+        if body_cost <= energy_capacity {
+            goals.remove(&key);
+            return true;
+        }
+
+        if goals.insert(key) {
+            info!(
+                "room {} can't afford a {} ({} energy) with {} energy capacity, skipping it",
+                room_name, spawn_goal.name, body_cost, energy_capacity
+            );
+        }
+        false
+    })
+}
+
+/// Returns the goal's count, only letting it go up once the higher count has held for
+/// `SPAWN_GOAL_SETTLE_TICKS`. Counts that depend on live state such as link energy flip back and
+/// forth, and every flip up would otherwise spawn a creep that's no longer needed a few ticks
+/// later. Counts going down take effect straight away since they never spawn anything, as do
+/// `UNSETTLED_SPAWN_GOALS`.
+fn get_settled_count(room_name: RoomName, spawn_goal: &SpawnGoal) -> u32 {
+    if config::UNSETTLED_SPAWN_GOALS.contains(&spawn_goal.name.as_str()) {
+        return spawn_goal.count;
+    }
+
+    let time = game::time();
+    super::SPAWN_GOAL_COUNTS.with(|counts| {
+        let mut counts = counts.borrow_mut();
+        let entry = counts
+            .entry((room_name, spawn_goal.name.clone()))
+            .or_insert(SettledCount {
+                settled: spawn_goal.count,
+                pending: spawn_goal.count,
+                pending_since: time,
+            });
+
+        if spawn_goal.count != entry.pending {
+            entry.pending = spawn_goal.count;
+            entry.pending_since = time;
+        }
+
+        if spawn_goal.count < entry.settled
+            || time - entry.pending_since >= config::SPAWN_GOAL_SETTLE_TICKS
+        {
+            entry.settled = spawn_goal.count;
+        }
+
+        entry.settled
+    })
+}
+
+/// Returns true if the creep fills a critical role and will die before a replacement could be
+/// spawned and walk over to it
+fn is_due_for_replacement(creep: &Creep, creep_type: &str) -> bool {
+    if !config::PRESPAWN_ROLES.contains(&creep_type) {
+        return false;
+    }
+
+    let ticks_to_live = match creep.ticks_to_live() {
+        Some(ticks_to_live) => ticks_to_live,
+        None => return false,
+    };
+
+    let spawn_time = creep.body().len() as u32 * CREEP_SPAWN_TIME;
+    let room = match creep.room() {
+        Some(room) => room,
+        None => return false,
+    };
+    let travel_time = room
+        .find(find::MY_SPAWNS, None)
+        .iter()
+        .map(|spawn| spawn.pos().get_range_to(creep.pos()))
+        .min()
+        .unwrap_or(0);
+
+    ticks_to_live <= spawn_time + travel_time
+}
+
+/// Pushes idle creeps away from spawns that are about to finish spawning but have no free tile
+/// around them, so the new creep isn't trapped
+pub fn unblock_spawns(task_manager: &TaskManager) {
+    for spawn in game::spawns().values() {
+        let spawning = spawn.spawning();
+        if spawning.is_none() || spawning.unwrap().remaining_time() > 1 {
+            continue;
+        }
+
+        let room = spawn.room().unwrap();
+        let x = spawn.pos().x().u8();
+        let y = spawn.pos().y().u8();
+        let area = room.look_at_area(
+            y.saturating_sub(1),
+            x.saturating_sub(1),
+            std::cmp::min(y + 1, 49),
+            std::cmp::min(x + 1, 49),
+        );
+
+        let mut blocked_tiles = HashSet::new();
+        let mut neighbours = Vec::new();
+        for o in area.iter() {
+            let tile = (o.x, o.y);
+            match &o.look_result {
+                LookResult::Terrain(Terrain::Wall) => {
+                    blocked_tiles.insert(tile);
+                }
+                LookResult::Structure(structure) => {
+                    let structure_type = structure.structure_type();
+                    if structure_type != StructureType::Road
+                        && structure_type != StructureType::Container
+                        && structure_type != StructureType::Rampart
+                    {
+                        blocked_tiles.insert(tile);
+                    }
+                }
+                LookResult::Creep(creep) => {
+                    blocked_tiles.insert(tile);
+                    neighbours.push(creep.clone());
+                }
+                _ => {}
+            }
+        }
+
+        // 9 tiles minus the spawn itself
+        let free_tile_count = 9 - blocked_tiles.len();
+        if free_tile_count > 0 {
+            continue;
+        }
+
+        for creep in neighbours {
+            let is_idle = creep
+                .try_id()
+                .map(|id| !task_manager.tasks.contains_key(&id))
+                .unwrap_or(false);
+            if !is_idle || !creep.my() {
+                continue;
+            }
+
+            if let Some(direction) = spawn.pos().get_direction_to(creep.pos()) {
+                info!(
+                    "moving {} away from blocked spawn {}",
+                    creep.name(),
+                    spawn.name()
+                );
+                let _ = creep.move_direction(direction);
+            }
+        }
+    }
+}
@@ -1,20 +1,33 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 use log::*;
-use screeps::StructureLink;
 use screeps::{
     find, game, Creep, HasHits, HasPosition, HasTypedId, MaybeHasTypedId, ObjectId,
     OwnedStructureProperties, Part, Position, ResourceType, Room, RoomName, RoomPosition,
-    SharedCreepProperties, Source, StructureObject, StructureProperties, StructureType,
+    SharedCreepProperties, Source, StructureContainer, StructureObject, StructureProperties,
+    StructureType,
 };
 
 mod attack;
+mod attack_structure;
+mod boost;
 mod build;
 mod claim;
+mod defend_rampart;
+mod drop;
+mod harvest_mineral;
 mod harvest_source;
 mod heal;
 mod idle;
 mod idle_until;
+mod kill_keeper;
+mod pickup;
+mod recycle;
+mod renew;
 mod repair;
 mod task;
 mod task_list;
@@ -25,14 +38,24 @@ mod upgrade;
 mod withdraw;
 
 pub use attack::AttackTask;
+pub use attack_structure::{AttackStructureTask, SiegeTarget};
+pub use boost::BoostTask;
 pub use build::BuildTask;
 pub use claim::ClaimTask;
+pub use defend_rampart::DefendRampartTask;
+pub use drop::DropTask;
+pub use harvest_mineral::HarvestMineralTask;
 pub use harvest_source::HarvestSourceTask;
 pub use heal::HealTask;
 pub use idle::IdleTask;
 pub use idle_until::IdleUntilTask;
+pub use kill_keeper::KillKeeperTask;
+pub use pickup::PickupTask;
+pub use recycle::RecycleTask;
+pub use renew::RenewTask;
 pub use repair::RepairTask;
 pub use task::Task;
+pub use task::TaskOutcome;
 pub use task::TaskType;
 pub use task_list::TaskList;
 pub use transfer::TransferTask;
@@ -41,7 +64,11 @@ pub use travel_dumb::TravelDumbTask;
 pub use upgrade::UpgradeTask;
 pub use withdraw::WithdrawTask;
 
-use crate::metadata::{ControllerLink, RoomInfo, SourceLink, StorageLink};
+use crate::logistics::EnergyGraph;
+use crate::metadata::{
+    ControllerLink, Feature, RoomInfo, RoomPhase, SourceLink, StorageLink, LAB_BOOST_ENERGY,
+    LAB_BOOST_MINERAL,
+};
 use crate::utils::{self, get_creep_type};
 use wasm_bindgen::JsValue;
 
@@ -51,7 +78,12 @@ pub struct TaskManager {
     pub tasks: TaskMap,
     working_creeps_by_room_and_type: HashMap<RoomName, HashMap<String, u32>>,
     working_creeps_by_room_and_pos: HashMap<RoomName, HashMap<Position, u32>>,
+    idle_ticks: HashMap<ObjectId<Creep>, u32>,
+    // the task icon each creep last said, so it's only said again when it changes
+    said_icons: HashMap<ObjectId<Creep>, String>,
     pub room_info_map: HashMap<RoomName, RoomInfo>,
+    // room task lists nobody took during this tick's assignment
+    unassigned_room_tasks: HashMap<RoomName, Vec<TaskList>>,
 }
 
 impl TaskManager {
@@ -61,7 +93,11 @@ impl TaskManager {
 
         for creep in creeps.values() {
             let creep_type = get_creep_type(&creep);
-            let room_name = creep.room().unwrap().name();
+            let room = creep.room();
+            if room.is_none() {
+                continue;
+            }
+            let room_name = room.unwrap().name();
 
             let count: &mut HashMap<String, u32> = working_creeps_by_room_and_type
                 .entry(room_name)
@@ -74,7 +110,10 @@ impl TaskManager {
             tasks: HashMap::new(),
             working_creeps_by_room_and_type,
             working_creeps_by_room_and_pos: HashMap::new(),
+            idle_ticks: HashMap::new(),
+            said_icons: HashMap::new(),
             room_info_map: HashMap::new(),
+            unassigned_room_tasks: HashMap::new(),
         }
     }
 
@@ -84,6 +123,74 @@ impl TaskManager {
         }
     }
 
+    /// Moves other creeps off the tiles source harvesters mine from, while a source harvester is
+    /// waiting nearby for one. Harvesters only keep up with a source from a tile next to both the
+    /// source and its link, or from the source's container.
+    pub fn unblock_mining_tiles(&self) {
+        for room_info in self.room_info_map.values() {
+            if !utils::is_mine(&room_info.room) {
+                continue;
+            }
+
+            let creeps = room_info.room.find(find::MY_CREEPS, None);
+            let harvesters = creeps
+                .iter()
+                .filter(|c| get_creep_type(c) == "source_harvester")
+                .collect::<Vec<_>>();
+            if harvesters.is_empty() {
+                continue;
+            }
+
+            let containers = room_info
+                .structures
+                .iter()
+                .filter_map(|s| match s {
+                    StructureObject::StructureContainer(container) => Some(container.pos()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            for source in room_info.room.find(find::SOURCES, None) {
+                let links = room_info
+                    .links
+                    .source_links
+                    .iter()
+                    .filter(|SourceLink(_, s)| s.id() == source.id())
+                    .map(|SourceLink(link, _)| link.pos())
+                    .collect::<Vec<_>>();
+                let is_mining_tile = |pos: Position| {
+                    pos.is_near_to(source.pos())
+                        && (links.iter().any(|link| pos.is_near_to(*link))
+                            || containers.contains(&pos))
+                };
+
+                let harvester_waiting = harvesters.iter().any(|h| {
+                    !is_mining_tile(h.pos())
+                        && h.pos()
+                            .in_range_to(source.pos(), crate::config::SOURCE_QUEUE_RANGE as u32)
+                });
+                if !harvester_waiting {
+                    continue;
+                }
+
+                for creep in creeps.iter() {
+                    if get_creep_type(creep) == "source_harvester" || !is_mining_tile(creep.pos()) {
+                        continue;
+                    }
+
+                    if let Some(direction) = source.pos().get_direction_to(creep.pos()) {
+                        info!(
+                            "moving {} off a mining tile at {}",
+                            creep.name(),
+                            creep.pos()
+                        );
+                        let _ = creep.move_direction(direction);
+                    }
+                }
+            }
+        }
+    }
+
     fn execute_links(&self) {
         for room_info in self.room_info_map.values() {
             // info!(
@@ -93,6 +200,10 @@ impl TaskManager {
             //     link_map.controller_links.len(),
             //     link_map.unknown_links.len()
             // );
+            if !room_info.is_feature_enabled(Feature::Links) {
+                continue;
+            }
+
             let link_map = &room_info.links;
             'source_loop: for SourceLink(source_link, _source) in link_map.source_links.iter() {
                 if source_link.cooldown() > 0 {
@@ -144,7 +255,7 @@ impl TaskManager {
         }
     }
 
-    /// Removes tasks for creeps that no longer exist
+    /// Removes tasks, idle counts and route stuck counts for creeps that no longer exist
     pub fn clean_up_tasks(&mut self) {
         let mut tasks_to_remove = Vec::new();
         for (creep_id, _task) in self.tasks.iter() {
@@ -156,6 +267,79 @@ impl TaskManager {
         for creep_id in tasks_to_remove {
             self.tasks.remove(&creep_id);
         }
+
+        self.idle_ticks
+            .retain(|creep_id, _| game::get_object_by_id_typed(creep_id).is_some());
+        self.said_icons
+            .retain(|creep_id, _| game::get_object_by_id_typed(creep_id).is_some());
+        super::ROUTE_STUCK_COUNTS.with(|stuck_counts| {
+            stuck_counts
+                .borrow_mut()
+                .retain(|creep_name, _| game::creeps().get(creep_name.clone()).is_some())
+        });
+    }
+
+    /// Removes the task list of the creep with the given name so it becomes idle
+    pub fn reset_creep_task(&mut self, creep_name: &str) {
+        let creep = game::creeps().get(creep_name.to_string());
+        if creep.is_none() {
+            error!("cannot reset task for nonexistent creep {}", creep_name);
+            return;
+        }
+
+        let creep = creep.unwrap();
+        if let Some(creep_id) = creep.try_id() {
+            if let Some(task_list) = self.tasks.remove(&creep_id) {
+                info!("{} dropped {:?}", creep_name, task_list);
+                for key in ["task", "task_list"] {
+                    let _ = js_sys::Reflect::set(
+                        &creep.memory(),
+                        &JsValue::from_str(key),
+                        &JsValue::UNDEFINED,
+                    );
+                }
+            } else {
+                info!("{} has no task to reset", creep_name);
+            }
+        }
+    }
+
+    /// Logs every creep's role and current task along with the working creep counts used to spread
+    /// creeps out between rooms and targets
+    pub fn dump_tasks(&self) {
+        info!("==== tasks ({} creeps) ====", self.tasks.len());
+        for (creep_id, task_list) in self.tasks.iter() {
+            let creep = creep_id.resolve();
+            if creep.is_none() {
+                info!("{:?} (dead): {:?}", creep_id, task_list);
+                continue;
+            }
+            let creep = creep.unwrap();
+
+            let task = task_list.current_task();
+            info!(
+                "{} [{}]: {:?} at {:?} (memory: {:?})",
+                creep.name(),
+                get_creep_type(&creep),
+                task,
+                task.and_then(|t| t.get_target_pos()),
+                utils::get_memory_position(&creep, "target_pos")
+            );
+        }
+
+        info!("==== working creeps by room and type ====");
+        for (room_name, counts) in self.working_creeps_by_room_and_type.iter() {
+            info!("{}: {:?}", room_name, counts);
+        }
+
+        info!("==== working creeps by room and position ====");
+        for (room_name, counts) in self.working_creeps_by_room_and_pos.iter() {
+            let counts = counts
+                .iter()
+                .map(|(pos, count)| format!("({}, {}): {}", pos.x().u8(), pos.y().u8(), count))
+                .collect::<Vec<_>>();
+            info!("{}: {}", room_name, counts.join(", "));
+        }
     }
 
     fn recalculate_working_creeps_by_room_and_type(&mut self) {
@@ -177,7 +361,11 @@ impl TaskManager {
                 let room_name = task
                     .get_target_pos()
                     .map(|p| p.room_name())
-                    .unwrap_or(creep.room().unwrap().name());
+                    .or_else(|| creep.room().map(|room| room.name()));
+                if room_name.is_none() {
+                    continue;
+                }
+                let room_name = room_name.unwrap();
 
                 let count: &mut HashMap<String, u32> = self
                     .working_creeps_by_room_and_type
@@ -208,7 +396,11 @@ impl TaskManager {
                 let room_name = task
                     .get_target_pos()
                     .map(|p| p.room_name())
-                    .unwrap_or(creep.room().unwrap().name());
+                    .or_else(|| creep.room().map(|room| room.name()));
+                if room_name.is_none() {
+                    continue;
+                }
+                let room_name = room_name.unwrap();
 
                 if let Some(target_pos) = task.get_target_pos() {
                     let count: &mut HashMap<Position, u32> = self
@@ -224,8 +416,16 @@ impl TaskManager {
 
     pub fn set_task_list(&mut self, creep: &Creep, task_list: TaskList) {
         if let Some(creep_id) = creep.try_id() {
+            self.idle_ticks.remove(&creep_id);
             let task = task_list.current_task().unwrap();
             update_creep_memory(creep, &task_list);
+            if let Some(primary_task) = task_list.get_primary_task() {
+                let _ = js_sys::Reflect::set(
+                    &creep.memory(),
+                    &JsValue::from_str("last_task_type"),
+                    &JsValue::from_str(&format!("{:?}", primary_task.get_type())),
+                );
+            }
             if let Some(pos) = task.get_target_pos() {
                 self.update_working_creeps_by_room(creep, pos);
             }
@@ -242,8 +442,14 @@ impl TaskManager {
             *room.entry(target_pos).or_insert(0) += 1;
         }
 
+        let current_room = creep.room();
+        if current_room.is_none() {
+            return;
+        }
+        let current_room_name = current_room.unwrap().name();
+
         // Keep track of the room switch
-        if target_pos.room_name() != creep.room().unwrap().name() {
+        if target_pos.room_name() != current_room_name {
             // info!(
             //     "{} switched rooms from {} to {}",
             //     creep.name(),
@@ -259,7 +465,7 @@ impl TaskManager {
             }
             if let Some(room) = self
                 .working_creeps_by_room_and_type
-                .get_mut(&creep.room().unwrap().name())
+                .get_mut(&current_room_name)
             {
                 *room.entry(get_creep_type(creep)).or_insert(0) -= 1;
             }
@@ -269,93 +475,165 @@ impl TaskManager {
     pub fn execute_tasks(&mut self) {
         self.execute_links();
 
-        let completed_tasks = Rc::new(RefCell::new(Vec::new()));
-        let cancelled_tasks = Rc::new(RefCell::new(Vec::new()));
+        let cancelled_creeps = self.execute_task_lists(None);
+        self.reassign_cancelled_creeps(cancelled_creeps);
+    }
+
+    /// Gives creeps whose task was cancelled this tick one of the room task lists nobody took
+    /// during assignment, and runs it straight away instead of leaving the creep idle until the
+    /// next tick. Skipped once the tick has used `SAME_TICK_REASSIGN_CPU_LIMIT`.
+    fn reassign_cancelled_creeps(&mut self, cancelled_creeps: Vec<ObjectId<Creep>>) {
+        if cancelled_creeps.is_empty()
+            || game::cpu::get_used() >= crate::config::SAME_TICK_REASSIGN_CPU_LIMIT
+        {
+            return;
+        }
+
+        let mut room_tasks_map = std::mem::take(&mut self.unassigned_room_tasks);
+        let mut reassigned_creeps = HashSet::new();
+        for creep_id in cancelled_creeps {
+            let creep = creep_id.resolve();
+            if creep.is_none() {
+                continue;
+            }
+            let creep = creep.unwrap();
+
+            let room_tasks = creep
+                .room()
+                .and_then(|room| room_tasks_map.get_mut(&room.name()));
+            if let Some(room_tasks) = room_tasks {
+                if let Some(task_list) = self.get_task_list_for_creep(&creep, room_tasks) {
+                    info!(
+                        "{} picked up {:?} in the same tick its task was cancelled",
+                        creep.name(),
+                        task_list.get_primary_task()
+                    );
+                    self.set_task_list(&creep, task_list);
+                    reassigned_creeps.insert(creep_id);
+                }
+            }
+        }
+        self.unassigned_room_tasks = room_tasks_map;
+
+        // creeps whose new task is cancelled as well wait for the next tick
+        if !reassigned_creeps.is_empty() {
+            self.execute_task_lists(Some(&reassigned_creeps));
+        }
+    }
+
+    /// Runs the current task of every creep, or only of the given creeps. Returns the creeps that
+    /// were left without a task list because their last task was cancelled.
+    fn execute_task_lists(
+        &mut self,
+        only_creeps: Option<&HashSet<ObjectId<Creep>>>,
+    ) -> Vec<ObjectId<Creep>> {
+        let mut cancelled_creeps = Vec::new();
+
+        let finished_tasks: Rc<RefCell<Vec<(ObjectId<Creep>, TaskOutcome)>>> =
+            Rc::new(RefCell::new(Vec::new()));
         let switch_tasks: Rc<RefCell<TaskMap>> = Rc::new(RefCell::new(HashMap::new()));
 
         for (creep_id, task_list) in self.tasks.iter_mut() {
+            if only_creeps.is_some_and(|only_creeps| !only_creeps.contains(creep_id)) {
+                continue;
+            }
+
             if let Some(creep) = game::get_object_by_id_typed(creep_id) {
-                let completed_tasks_clone = completed_tasks.clone();
-                let cancelled_tasks_clone = cancelled_tasks.clone();
+                // creeps on an exit tile can briefly be without a room, they resume next tick
+                let room = creep.room();
+                if room.is_none() || utils::is_room_disabled(room.unwrap().name()) {
+                    continue;
+                }
+
+                let completed_tasks_clone = finished_tasks.clone();
+                let cancelled_tasks_clone = finished_tasks.clone();
                 let switch_tasks_clone = switch_tasks.clone();
                 if let Some(task) = task_list.current_task_mut() {
                     let cpu_start = screeps::game::cpu::get_used();
                     task.execute(
                         &creep,
-                        Box::new(move |creep_id| completed_tasks_clone.borrow_mut().push(creep_id)),
-                        Box::new(move |creep_id| cancelled_tasks_clone.borrow_mut().push(creep_id)),
+                        Box::new(move |creep_id| {
+                            completed_tasks_clone
+                                .borrow_mut()
+                                .push((creep_id, TaskOutcome::Completed))
+                        }),
+                        Box::new(move |creep_id, outcome| {
+                            cancelled_tasks_clone.borrow_mut().push((creep_id, outcome))
+                        }),
                         Box::new(move |creep_id, task| {
                             switch_tasks_clone.borrow_mut().insert(creep_id, task);
                         }),
                     );
                     let cpu_end = screeps::game::cpu::get_used();
 
-                    let mut msg = task.get_icon();
-                    let display_execution_time = true;
-                    if display_execution_time {
-                        msg = format!("{} {:.3}", msg, (cpu_end - cpu_start));
+                    // saying costs CPU, so creeps only speak up when their task changes
+                    let icon = task.get_icon();
+                    if self.said_icons.get(creep_id) != Some(&icon) {
+                        let mut msg = icon.clone();
+                        let display_execution_time = true;
+                        if display_execution_time {
+                            msg = format!("{} {:.3}", msg, (cpu_end - cpu_start));
+                        }
+                        let _ = creep.say(&msg, false);
+                        self.said_icons.insert(*creep_id, icon);
+                    }
+
+                    // these tasks leave the creep's work parts free for the tick
+                    if matches!(
+                        task.get_type(),
+                        TaskType::Travel
+                            | TaskType::TravelDumb
+                            | TaskType::Transfer
+                            | TaskType::Withdraw
+                            | TaskType::Pickup
+                    ) {
+                        repair_in_passing(&creep);
                     }
-                    let _ = creep.say(&msg, false);
                 }
             }
         }
-        for completed_task in completed_tasks.borrow().iter() {
-            let creep = completed_task.resolve().unwrap();
-            let _ = creep.say("✅", false);
-            info!(
-                "{} completed {:?}",
-                game::get_object_by_id_typed(completed_task).unwrap().name(),
-                self.tasks
-                    .get(completed_task)
-                    .unwrap()
-                    .current_task()
-                    .unwrap(),
-            );
-
-            {
-                let task_list = self.tasks.get(completed_task).unwrap();
-                update_creep_memory(&creep, task_list);
-            }
-
-            let task_list = self.tasks.get_mut(completed_task).unwrap();
-            if let Some(task) = task_list.next_task() {
-                if let Some(pos) = task.get_target_pos() {
-                    self.update_working_creeps_by_room(&creep, pos)
-                }
+        for (finished_task, outcome) in finished_tasks.borrow().iter() {
+            let creep = finished_task.resolve().unwrap();
+            if *outcome == TaskOutcome::Completed {
+                let _ = creep.say("✅", false);
+                info!(
+                    "{} completed {:?}",
+                    creep.name(),
+                    self.tasks
+                        .get(finished_task)
+                        .unwrap()
+                        .current_task()
+                        .unwrap(),
+                );
             } else {
-                self.tasks.remove(completed_task);
+                let _ = creep.say("❌", false);
+                info!(
+                    "{} did not successfully complete {:?}: {:?}",
+                    creep.name(),
+                    self.tasks
+                        .get(finished_task)
+                        .unwrap()
+                        .current_task()
+                        .unwrap(),
+                    outcome,
+                );
             }
 
-            // info!(
-            //     "current task {:?}",
-            //     self.tasks.get(completed_task).unwrap().current_task()
-            // );
-        }
-        for cancelled_task in cancelled_tasks.borrow().iter() {
-            let creep = cancelled_task.resolve().unwrap();
-            let _ = creep.say("❌", false);
-            info!(
-                "{} did not successfully complete {:?}",
-                game::get_object_by_id_typed(cancelled_task).unwrap().name(),
-                self.tasks
-                    .get(cancelled_task)
-                    .unwrap()
-                    .current_task()
-                    .unwrap(),
-            );
-
             {
-                let task_list = self.tasks.get(cancelled_task).unwrap();
+                let task_list = self.tasks.get(finished_task).unwrap();
                 update_creep_memory(&creep, task_list);
             }
 
-            let task_list = self.tasks.get_mut(cancelled_task).unwrap();
+            let task_list = self.tasks.get_mut(finished_task).unwrap();
             if let Some(task) = task_list.next_task() {
                 if let Some(pos) = task.get_target_pos() {
                     self.update_working_creeps_by_room(&creep, pos)
                 }
             } else {
-                self.tasks.remove(cancelled_task);
+                self.tasks.remove(finished_task);
+                if *outcome != TaskOutcome::Completed {
+                    cancelled_creeps.push(*finished_task);
+                }
             }
         }
         for (creep_id, task_list) in switch_tasks.borrow_mut().drain() {
@@ -370,6 +648,8 @@ impl TaskManager {
                 self.set_task_list(&creep, task_list);
             }
         }
+
+        cancelled_creeps
     }
 
     pub fn assign_tasks(&mut self) -> Vec<TaskList> {
@@ -381,6 +661,13 @@ impl TaskManager {
 
         let mut room_tasks_map = HashMap::new();
         for room in game::rooms().values() {
+            if utils::is_room_disabled(room.name()) {
+                continue;
+            }
+            // evacuating rooms only hand out evacuation work to their own creeps
+            if utils::is_room_evacuating(room.name()) {
+                continue;
+            }
             room_tasks_map.insert(room.name(), self.get_room_task_lists(room));
             utils::log_cpu_usage("assign tasks - get room tasks");
         }
@@ -391,6 +678,20 @@ impl TaskManager {
                 continue;
             }
             let current_room = current_room.unwrap();
+            if utils::is_room_disabled(current_room.name()) {
+                continue;
+            }
+
+            if let Some(task) = self.get_room_evacuation_task_list(&creep) {
+                self.set_task_list(&creep, task);
+                continue;
+            }
+
+            // combat creeps don't leave home until they've been boosted
+            if let Some(task) = self.get_boost_task_list(&creep) {
+                self.set_task_list(&creep, task);
+                continue;
+            }
 
             if let Some(task) = self.get_task_list_for_creep(&creep, &mut flag_task_lists) {
                 self.set_task_list(&creep, task);
@@ -436,12 +737,27 @@ impl TaskManager {
             utils::log_cpu_usage("assign tasks - creep loop - other toom tasks");
 
             if let Some(task) = self.get_default_task_list_for_creep(&creep) {
-                self.set_task_list(&creep, task)
+                self.set_task_list(&creep, task);
+                continue;
+            }
+
+            if let Some(task) = self.get_recycle_task_list(&creep) {
+                self.set_task_list(&creep, task);
+                continue;
+            }
+
+            // renewing isn't work, so it's kept out of set_task_list to leave the creep's idle
+            // ticks counting towards recycling
+            if let (Some(creep_id), Some(task)) = (creep.try_id(), self.get_renew_task_list(&creep))
+            {
+                self.tasks.insert(creep_id, task);
             }
 
             utils::log_cpu_usage("assign tasks - creep loop - default task");
         }
 
+        self.unassigned_room_tasks = room_tasks_map;
+
         self.recalculate_working_creeps_by_room_and_type();
 
         utils::log_cpu_usage(
@@ -462,15 +778,41 @@ impl TaskManager {
         creep: &Creep,
         task_lists: &mut Vec<TaskList>,
     ) -> Option<TaskList> {
+        let traced = crate::config::TRACE_ASSIGNMENT_CREEP == Some(creep.name().as_str());
+        if traced {
+            trace_candidate_task_lists(creep, task_lists);
+        }
+
+        // a room waiting on energy to spawn has its spawn and extension refills at the front of
+        // its task lists, and those shouldn't be passed over for work near the creep
+        let starved_room = get_energy_starved_room(task_lists);
+        if starved_room.is_some() {
+            if traced {
+                trace!(
+                    "{} skipped affinity, the room is waiting on energy to spawn",
+                    creep.name()
+                );
+            }
+        } else if let Some(index) = get_affine_task_list_index(creep, task_lists) {
+            if traced {
+                trace!(
+                    "{} picked {:?} by affinity",
+                    creep.name(),
+                    task_lists[index].get_primary_task()
+                );
+            }
+            return Some(task_lists.remove(index));
+        }
+
         // (index, task)
         let mut similar_task_lists: Vec<(usize, &TaskList)> = vec![];
         for (index, task_list) in task_lists.iter().enumerate() {
             let task = task_list.current_task().unwrap();
-            if similar_task_lists.is_empty() && can_creep_handle_task(creep, task) {
-                if task.requires_energy()
-                    && creep.store().get_used_capacity(Some(ResourceType::Energy)) > 0
-                    || !task.requires_energy()
-                {
+            if similar_task_lists.is_empty()
+                && can_creep_handle_task(creep, task_list)
+                && can_creep_reach_in_time(creep, task_list)
+            {
+                if task.requires_energy() && utils::energy(creep) > 0 || !task.requires_energy() {
                     similar_task_lists.push((index, task_list));
                     continue;
                 }
@@ -484,7 +826,9 @@ impl TaskManager {
                 if primary_task.get_type() == first_primary_task.get_type()
                     && first_task.get_type() == task.get_type()
                 {
-                    similar_task_lists.push((index, task_list));
+                    if can_creep_reach_in_time(creep, task_list) {
+                        similar_task_lists.push((index, task_list));
+                    }
                 } else {
                     break;
                 }
@@ -493,11 +837,36 @@ impl TaskManager {
 
         // Default task
         if similar_task_lists.is_empty() {
+            if traced {
+                trace!("{} found no task list it can take", creep.name());
+            }
             return None;
         }
 
+        // other transfers that happen to look alike can't win over a spawn or extension refill
+        if let Some(room_info) =
+            starved_room.and_then(|room_name| self.room_info_map.get(&room_name))
+        {
+            let spawn_fill_task_lists = similar_task_lists
+                .iter()
+                .filter(|(_, task_list)| is_spawn_fill(room_info, task_list))
+                .copied()
+                .collect::<Vec<_>>();
+            if !spawn_fill_task_lists.is_empty() {
+                similar_task_lists = spawn_fill_task_lists;
+            }
+        }
+
         if similar_task_lists.len() == 1 {
-            return Some(task_lists.remove(similar_task_lists.get(0).unwrap().0));
+            let index = similar_task_lists.get(0).unwrap().0;
+            if traced {
+                trace!(
+                    "{} picked {:?}, the only match",
+                    creep.name(),
+                    task_lists[index].get_primary_task()
+                );
+            }
+            return Some(task_lists.remove(index));
         }
 
         // (index, distance to target)
@@ -520,7 +889,17 @@ impl TaskManager {
             .collect::<Vec<(usize, u32)>>();
 
         tasks_by_value.sort_by(|a, b| a.1.cmp(&b.1));
-        // info!("sorted tasks: {:?}", tasks_by_value);
+        if traced {
+            for (index, value) in tasks_by_value.iter() {
+                trace!(
+                    "{} sort value {} for [{}] {:?}",
+                    creep.name(),
+                    value,
+                    index,
+                    task_lists[*index].get_primary_task()
+                );
+            }
+        }
 
         let shortest_distance_idx = tasks_by_value.first().unwrap().0;
 
@@ -560,6 +939,125 @@ impl TaskManager {
                     flag.remove();
                 }
             }
+
+            // attack flags are placed in the room to siege. Only the highest priority structures
+            // are handed out, attackers spread between them by distance
+            if flag.name().starts_with("attack", 0) {
+                let siege_target = SiegeTarget::from_flag_name(&String::from(flag.name()));
+
+                // without vision, melee creeps head to the flag by default
+                let room = flag.room();
+                if room.is_none() {
+                    continue;
+                }
+                let room = room.unwrap();
+
+                let targets = room
+                    .find(find::HOSTILE_STRUCTURES, None)
+                    .into_iter()
+                    .filter(|s| {
+                        !matches!(
+                            s.structure_type(),
+                            StructureType::Controller | StructureType::KeeperLair
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if targets.is_empty() {
+                    info!("nothing left to attack in room {}", room.name());
+                    flag.remove();
+                    continue;
+                }
+
+                let top_rank = targets
+                    .iter()
+                    .map(|s| siege_target.rank(s.structure_type()))
+                    .min()
+                    .unwrap();
+                for target in targets {
+                    if siege_target.rank(target.structure_type()) != top_rank {
+                        continue;
+                    }
+                    if let Some(id) = target.as_structure().try_id() {
+                        task_lists.push(TaskList::new(
+                            vec![Box::new(AttackStructureTask::new(id))],
+                            false,
+                            0,
+                        ));
+                    }
+                }
+            }
+
+            // demolish flags are placed on a storage, terminal or container that's being moved.
+            // Its contents are carried to the closest other store before it's destroyed
+            if flag.name().starts_with("demolish", 0) {
+                let room_info = self.room_info_map.get(&flag.pos().room_name());
+                if room_info.is_none() {
+                    continue;
+                }
+                let room_info = room_info.unwrap();
+
+                let structure = room_info.structures.iter().find(|s| {
+                    s.pos() == flag.pos()
+                        && matches!(
+                            s,
+                            StructureObject::StructureStorage(_)
+                                | StructureObject::StructureTerminal(_)
+                                | StructureObject::StructureContainer(_)
+                        )
+                });
+                if structure.is_none() {
+                    info!(
+                        "no store to demolish under flag {}",
+                        String::from(flag.name())
+                    );
+                    flag.remove();
+                    continue;
+                }
+                let structure = structure.unwrap();
+
+                let store = structure.as_has_store().unwrap().store();
+                let resource = store
+                    .store_types()
+                    .into_iter()
+                    .max_by_key(|r| store.get_used_capacity(Some(*r)));
+                if resource.is_none() {
+                    info!(
+                        "{:?} at {} is empty, destroying it",
+                        structure.structure_type(),
+                        structure.pos()
+                    );
+                    structure.as_structure().destroy().unwrap_or_else(|e| {
+                        info!("couldn't destroy structure: {:?}", e);
+                    });
+                    flag.remove();
+                    continue;
+                }
+
+                if !self.is_pos_being_worked_on(&room_info.room.name(), &structure.pos(), 2) {
+                    if let Some(task_list) =
+                        get_evacuation_task_list(room_info, structure, resource.unwrap())
+                    {
+                        task_lists.push(task_list);
+                    }
+                }
+            }
+
+            // keeper flags are placed on the lair to guard. The task repeats so the creep goes back
+            // to the lair after every kill
+            if flag.name().starts_with("keepers", 0) {
+                let lair_pos = flag.pos();
+                let is_guarded = self.tasks.values().any(|task_list| {
+                    task_list.get_primary_task().map(|t| t.get_type()) == Some(TaskType::KillKeeper)
+                        && task_list
+                            .get_primary_task()
+                            .and_then(|t| t.get_target_pos())
+                            == Some(lair_pos)
+                });
+                if !is_guarded {
+                    let task = Box::new(KillKeeperTask::new(lair_pos));
+                    task_lists.push(TaskList::new(vec![task], true, 0));
+                }
+            }
         }
 
         task_lists
@@ -575,26 +1073,57 @@ impl TaskManager {
 
         let mut tasks: Vec<TaskList> = Vec::new();
 
-        let room_info = self.room_info_map.get(&room.name()).unwrap();
+        // rooms we only just gained vision of won't have been scanned yet
+        let room_info = self.room_info_map.get(&room.name());
+        if room_info.is_none() {
+            return Vec::new();
+        }
+        let room_info = room_info.unwrap();
 
         let structures = &room_info.structures;
         let my_structures = &room_info.my_structures;
         let construction_sites = &room_info.construction_sites;
-        let enemy_creeps = room.find(find::HOSTILE_CREEPS, None);
-        let storage = my_structures
+        let enemy_creeps = &room_info.hostile_creeps;
+        // tasks top up from whichever of these is closest to where they're headed. Controller
+        // containers are left for the upgraders.
+        let controller_containers = room_info.get_controller_containers();
+        let energy_stores = structures
             .iter()
-            .filter(|s| {
-                s.structure_type() == StructureType::Storage
-                    && s.as_has_store()
-                        .unwrap()
-                        .store()
-                        .get_used_capacity(Some(ResourceType::Energy))
-                        > 0
+            .filter(|s| match s {
+                StructureObject::StructureStorage(storage) => {
+                    storage.my()
+                        && storage
+                            .store()
+                            .get_used_capacity(Some(ResourceType::Energy))
+                            > 0
+                }
+                StructureObject::StructureContainer(container) => {
+                    !controller_containers
+                        .iter()
+                        .any(|c| c.id() == container.id())
+                        && container
+                            .store()
+                            .get_used_capacity(Some(ResourceType::Energy))
+                            > 0
+                }
+                _ => false,
             })
-            .last();
+            .collect::<Vec<_>>();
 
         utils::log_cpu_usage("get room task lists - get data");
 
+        // hold the ramparts closest to the enemy before chasing anything into the open
+        for rampart in room_info.get_threatened_ramparts() {
+            if self.is_pos_being_worked_on(&room.name(), &rampart.pos(), 1) {
+                continue;
+            }
+            tasks.push(TaskList::new(
+                vec![Box::new(DefendRampartTask::new(rampart.id()))],
+                false,
+                0,
+            ));
+        }
+
         // attack
         if !enemy_creeps.is_empty() {
             for enemy_creep in enemy_creeps {
@@ -605,6 +1134,17 @@ impl TaskManager {
             }
         }
 
+        // invader cores block mining in reserved rooms, so clear them out
+        for invader_core in crate::intel::get_clearable_invader_cores(room_info) {
+            if let Some(id) = invader_core.as_structure().try_id() {
+                tasks.push(TaskList::new(
+                    vec![Box::new(AttackStructureTask::new(id))],
+                    false,
+                    0,
+                ));
+            }
+        }
+
         // controller: if the downgrade time is less than 10000 ticks, upgrade
         if controller.my() && controller.is_active() {
             if controller.ticks_to_downgrade() < 9000 {
@@ -615,7 +1155,7 @@ impl TaskManager {
                 ));
             }
 
-            if controller.level() < 2 {
+            if room_info.phase() == RoomPhase::Bootstrap {
                 tasks.push(TaskList::new(
                     vec![Box::new(UpgradeTask::new(controller.id()))],
                     false,
@@ -625,14 +1165,7 @@ impl TaskManager {
         }
 
         // transfer energy from link to storage
-        for StorageLink(storage_link, storage) in self
-            .room_info_map
-            .get(&room.name())
-            .unwrap()
-            .links
-            .storage_links
-            .iter()
-        {
+        for StorageLink(storage_link, storage) in room_info.links.storage_links.iter() {
             if storage
                 .store()
                 .get_free_capacity(Some(ResourceType::Energy))
@@ -648,93 +1181,88 @@ impl TaskManager {
                 > 0
             {
                 let transfer_task = Box::new(TransferTask::new(storage.id()));
-                let withdraw_task = Box::new(WithdrawTask::new(storage_link.id()));
+                let withdraw_task =
+                    Box::new(WithdrawTask::new(storage_link.id()).queue_when_crowded());
                 tasks.push(TaskList::new(vec![withdraw_task, transfer_task], false, 1));
             }
         }
 
         utils::log_cpu_usage("get room task lists - link to storage tasks");
 
-        // extensions
-        let extensions = my_structures
-            .iter()
-            .filter(|s| s.structure_type() == StructureType::Extension);
-        let mut extension_transfer_tasks_exist = false;
-        for extension in extensions {
-            if let StructureObject::StructureExtension(extension) = extension {
-                if extension
-                    .store()
-                    .get_free_capacity(Some(ResourceType::Energy))
-                    > 0
-                {
-                    if self.is_pos_being_worked_on(&room.name(), &extension.pos(), 1) {
-                        continue;
-                    }
-
-                    let transfer_task = Box::new(TransferTask::new(extension.id()));
+        // move minerals from storage to the terminal so they can be traded
+        if let Some(task_list) = self.get_storage_rebalance_task_list(room_info) {
+            tasks.push(task_list);
+        }
 
-                    tasks.push(allow_withdrawal_from_storage(storage, transfer_task));
+        utils::log_cpu_usage("get room task lists - storage rebalance tasks");
 
-                    extension_transfer_tasks_exist = true;
-                    // break;
-                }
-            }
+        // RCL8 controllers can't use all the energy sent to them
+        for task_list in self.get_controller_overflow_task_lists(room_info) {
+            tasks.push(task_list);
         }
 
-        utils::log_cpu_usage("get room task lists - extension tasks");
-
-        // spawn
-        let spawns = my_structures
-            .iter()
-            .filter(|s| s.structure_type() == StructureType::Spawn);
+        utils::log_cpu_usage("get room task lists - controller overflow tasks");
 
-        for spawn in spawns {
-            if let StructureObject::StructureSpawn(spawn) = spawn {
-                if spawn.is_active()
-                    && spawn.store().get_free_capacity(Some(ResourceType::Energy)) > 0
-                {
-                    if let Some(id) = spawn.try_id() {
-                        let transfer_task = Box::new(TransferTask::new(id));
+        // spawns, extensions, towers and controller containers, most valuable first. When a spawn
+        // is waiting on energy to spawn a creep, refilling it comes before anything else in the
+        // room
+        let spawning_blocked =
+            super::ENERGY_STARVED_ROOMS.with(|rooms| rooms.borrow().contains(&room.name()));
+        let mut spawn_fill_tasks = Vec::new();
+        let mut extension_transfer_tasks_exist = false;
+        let deliveries = EnergyGraph::new(room_info)
+            .get_deliveries(|pos| self.get_working_creep_count(&room.name(), &pos));
+        for delivery in deliveries {
+            if matches!(delivery.demand, StructureObject::StructureExtension(_)) {
+                extension_transfer_tasks_exist = true;
+            }
 
-                        tasks.push(allow_withdrawal_from_storage(storage, transfer_task));
-                    }
+            if let Some(task_list) = delivery.to_task_list() {
+                if spawning_blocked && delivery.is_spawn_fill() {
+                    spawn_fill_tasks.push(task_list);
+                } else {
+                    tasks.push(task_list);
                 }
             }
         }
+        tasks.splice(0..0, spawn_fill_tasks);
 
-        utils::log_cpu_usage("get room task lists - spawn tasks");
+        utils::log_cpu_usage("get room task lists - fill tasks");
 
-        // towers
-        let towers = my_structures
-            .iter()
-            .filter(|s| s.structure_type() == StructureType::Tower);
-        for tower in towers {
-            if let StructureObject::StructureTower(tower) = tower {
-                if tower.is_active()
-                    && tower.store().get_free_capacity(Some(ResourceType::Energy)) as u32
-                        > tower.store().get_capacity(Some(ResourceType::Energy)) / 2
+        // dropped energy
+        let energy_storage = my_structures.iter().find_map(|s| {
+            if let StructureObject::StructureStorage(storage) = s {
+                if storage
+                    .store()
+                    .get_free_capacity(Some(ResourceType::Energy))
+                    > 0
                 {
-                    if let Some(id) = tower.try_id() {
-                        tasks.push(allow_withdrawal_from_storage(
-                            storage,
-                            Box::new(TransferTask::new(id)),
-                        ));
-                    }
+                    return Some(storage);
+                }
+            }
+            None
+        });
+        for resource in room.find(find::DROPPED_RESOURCES, None).iter() {
+            if resource.resource_type() != ResourceType::Energy
+                || resource.amount() < crate::config::MIN_PICKUP_AMOUNT
+                || self.is_pos_being_worked_on(&room.name(), &resource.pos(), 1)
+            {
+                continue;
+            }
+
+            if let Some(id) = resource.try_id() {
+                let mut pickup_tasks: Vec<Box<dyn Task>> = vec![Box::new(PickupTask::new(id))];
+                if let Some(storage) = energy_storage {
+                    pickup_tasks.push(Box::new(TransferTask::new(storage.id())));
                 }
+                tasks.push(TaskList::new(pickup_tasks, false, 0));
             }
         }
 
-        utils::log_cpu_usage("get room task lists - tower tasks");
+        utils::log_cpu_usage("get room task lists - pickup tasks");
 
         // transfer energy from link to controller
-        for ControllerLink(controller_link, controller) in self
-            .room_info_map
-            .get(&room.name())
-            .unwrap()
-            .links
-            .controller_links
-            .iter()
-        {
+        for ControllerLink(controller_link, controller) in room_info.links.controller_links.iter() {
             if self.is_pos_being_worked_on(&room.name(), &controller_link.pos(), 1) {
                 continue;
             }
@@ -754,7 +1282,8 @@ impl TaskManager {
                 && controller_link.pos().in_range_to(controller.pos(), 2)
             {
                 let upgrade_task = Box::new(UpgradeTask::new(controller.id()));
-                let withdraw_task = Box::new(WithdrawTask::new(controller_link.id()));
+                let withdraw_task =
+                    Box::new(WithdrawTask::new(controller_link.id()).queue_when_crowded());
                 tasks.push(TaskList::new(vec![withdraw_task, upgrade_task], false, 1));
             }
         }
@@ -768,13 +1297,66 @@ impl TaskManager {
         //     return;
         // }
 
-        // construction sites
-        for construction_site in construction_sites.iter() {
-            if let Some(id) = construction_site.try_id() {
-                tasks.push(allow_withdrawal_from_storage(
-                    storage,
-                    Box::new(BuildTask::new(id)),
-                ));
+        // construction sites: builders can take as many as they like, but only a share of the
+        // workers build so the rest keep upgrading
+        let room_creeps = room.find(find::MY_CREEPS, None);
+        let worker_count = room_creeps
+            .iter()
+            .filter(|c| get_creep_type(c) == "worker")
+            .count() as u32;
+        let builder_count = room_creeps
+            .iter()
+            .filter(|c| get_creep_type(c) == "builder")
+            .count() as u32;
+        let build_task_limit = std::cmp::max(
+            builder_count + (worker_count as f32 * crate::config::BUILD_ENERGY_SHARE).ceil() as u32,
+            1,
+        );
+        let mut build_task_count = self
+            .tasks
+            .values()
+            .filter(|task_list| {
+                task_list
+                    .get_primary_task()
+                    .map(|t| {
+                        t.get_type() == TaskType::Build
+                            && t.get_target_pos().map(|p| p.room_name()) == Some(room.name())
+                    })
+                    .unwrap_or(false)
+            })
+            .count() as u32;
+        for construction_site in construction_sites.iter() {
+            if build_task_count >= build_task_limit {
+                break;
+            }
+
+            if let Some(id) = construction_site.try_id() {
+                let build_task = Box::new(BuildTask::new(id));
+
+                // builders near a container wait for haulers to refill it instead of trekking
+                // back to storage
+                let nearby_container = if crate::config::BUILDER_CONTAINER_RANGE == 0 {
+                    None
+                } else {
+                    structures.iter().find_map(|s| {
+                        if let StructureObject::StructureContainer(container) = s {
+                            if container.pos().in_range_to(
+                                construction_site.pos(),
+                                crate::config::BUILDER_CONTAINER_RANGE,
+                            ) {
+                                return Some(container);
+                            }
+                        }
+                        None
+                    })
+                };
+
+                if let Some(container) = nearby_container {
+                    tasks.push(allow_waiting_at_container(container, build_task));
+                } else {
+                    tasks.push(allow_withdrawal_from_storage(&energy_stores, build_task));
+                }
+                build_task_count += 1;
             }
         }
 
@@ -782,36 +1364,54 @@ impl TaskManager {
 
         // repair
         let mut repair_task_count = 0;
-        let repair_task_limit = 3;
-        for structure in structures.iter() {
+        let room_mode = crate::config::get_room_mode(room.name());
+        let repair_task_limit = (worker_count / room_mode.workers_per_repair_task()).clamp(
+            crate::config::MIN_REPAIR_TASKS,
+            crate::config::MAX_REPAIR_TASKS,
+        );
+        // structures about to decay away are repaired first, however the rest are ordered
+        let mut repair_candidates = structures
+            .iter()
+            .map(|s| (s, crate::metadata::is_about_to_decay(s)))
+            .collect::<Vec<_>>();
+        repair_candidates.sort_by_key(|(_, about_to_decay)| !about_to_decay);
+        for (structure, about_to_decay) in repair_candidates {
             let s = structure.as_structure();
             if self.is_pos_being_worked_on(&room.name(), &s.pos(), 1) {
                 continue;
             }
 
             if s.hits() < s.hits_max() / 2 {
+                let id = s.try_id().unwrap();
+                let mut repair_task = RepairTask::new(id);
+                if about_to_decay {
+                    repair_task = repair_task.with_urgency();
+                }
                 if let StructureObject::StructureWall(s) = structure {
                     if controller.level() < 3 {
                         continue;
                     }
 
-                    if s.hits() > 25000 {
+                    if s.hits() > room_mode.max_wall_hits() {
                         continue;
                     }
+                    repair_task = repair_task.with_max_hits(room_mode.max_wall_hits());
                 } else if let StructureObject::StructureRoad(s) = structure {
                     if s.hits() > s.hits_max() * 2 / 3 {
                         continue;
                     }
+                    repair_task =
+                        repair_task.with_hits_ratio(crate::config::ROAD_REPAIR_HITS_RATIO);
                 } else if let StructureObject::StructureRampart(s) = structure {
-                    if s.hits() > 100000 {
+                    if s.hits() > room_mode.max_rampart_hits() {
                         continue;
                     }
+                    repair_task = repair_task.with_max_hits(room_mode.max_rampart_hits());
                 }
 
-                let id = s.try_id().unwrap();
                 tasks.push(allow_withdrawal_from_storage(
-                    storage,
-                    Box::new(RepairTask::new(id)),
+                    &energy_stores,
+                    Box::new(repair_task),
                 ));
 
                 repair_task_count += 1;
@@ -827,6 +1427,275 @@ impl TaskManager {
         tasks
     }
 
+    /// Returns a task list moving one non-energy resource from storage to the terminal. Resources
+    /// a queued transfer is waiting on go first, then anything the terminal holds less than the
+    /// configured cap of.
+    fn get_storage_rebalance_task_list(&self, room_info: &RoomInfo) -> Option<TaskList> {
+        if !room_info.is_feature_enabled(Feature::Terminal) {
+            return None;
+        }
+
+        let mut storage = None;
+        let mut terminal = None;
+        for structure in room_info.my_structures.iter() {
+            match structure {
+                StructureObject::StructureStorage(s) => storage = Some(s),
+                StructureObject::StructureTerminal(t) => terminal = Some(t),
+                _ => {}
+            }
+        }
+
+        let storage = storage?;
+        let terminal = terminal?;
+        if terminal.store().get_free_capacity(None) <= 0
+            || self.is_pos_being_worked_on(&room_info.room.name(), &terminal.pos(), 1)
+        {
+            return None;
+        }
+
+        let stored_types = storage.store().store_types();
+        let resource = crate::terminal::get_terminal_shortfall(room_info)
+            .filter(|r| stored_types.contains(r))
+            .or_else(|| {
+                stored_types.iter().copied().find(|r| {
+                    *r != ResourceType::Energy
+                        && terminal.store().get_used_capacity(Some(*r))
+                            < crate::config::TERMINAL_RESOURCE_CAP
+                })
+            })?;
+
+        let withdraw_task = Box::new(WithdrawTask::with_resource(storage.id(), resource));
+        let transfer_task = Box::new(TransferTask::with_resource(terminal.id(), resource));
+        Some(TaskList::new(vec![withdraw_task, transfer_task], false, 1))
+    }
+
+    /// Returns task lists moving energy from nearly full controller links and containers back to
+    /// storage. Only applies at RCL8, where upgrading is capped at 15 energy per tick.
+    fn get_controller_overflow_task_lists(&self, room_info: &RoomInfo) -> Vec<TaskList> {
+        let mut task_lists = Vec::new();
+
+        let controller = room_info.controller.as_ref();
+        if controller.is_none() {
+            return task_lists;
+        }
+        let controller = controller.unwrap();
+        if !controller.my() || controller.level() < 8 {
+            return task_lists;
+        }
+
+        let storage = room_info.my_structures.iter().find_map(|s| {
+            if let StructureObject::StructureStorage(storage) = s {
+                return Some(storage);
+            }
+            None
+        });
+        if storage.is_none() {
+            return task_lists;
+        }
+        let storage = storage.unwrap();
+
+        let is_overflowing = |used: u32, capacity: u32| {
+            used as f32 >= capacity as f32 * crate::config::CONTROLLER_OVERFLOW_FILL_RATIO
+        };
+
+        for ControllerLink(link, _controller) in room_info.links.controller_links.iter() {
+            let store = link.store();
+            if is_overflowing(
+                store.get_used_capacity(Some(ResourceType::Energy)),
+                store.get_capacity(Some(ResourceType::Energy)),
+            ) && !self.is_pos_being_worked_on(&room_info.room.name(), &link.pos(), 1)
+            {
+                let withdraw_task = Box::new(WithdrawTask::new(link.id()).queue_when_crowded());
+                let transfer_task = Box::new(TransferTask::new(storage.id()));
+                task_lists.push(TaskList::new(vec![withdraw_task, transfer_task], false, 0));
+            }
+        }
+
+        // containers are only drained when there are no upgraders left to use them
+        let upgraders_working = self
+            .working_creeps_by_room_and_type
+            .get(&room_info.room.name())
+            .and_then(|counts| counts.get("upgrader"))
+            .map(|count| *count > 0)
+            .unwrap_or(false);
+        if upgraders_working {
+            return task_lists;
+        }
+
+        for container in room_info.get_controller_containers() {
+            let store = container.store();
+            if is_overflowing(
+                store.get_used_capacity(Some(ResourceType::Energy)),
+                store.get_capacity(Some(ResourceType::Energy)),
+            ) && !self.is_pos_being_worked_on(&room_info.room.name(), &container.pos(), 1)
+            {
+                let withdraw_task = Box::new(WithdrawTask::new(container.id()));
+                let transfer_task = Box::new(TransferTask::new(storage.id()));
+                task_lists.push(TaskList::new(vec![withdraw_task, transfer_task], false, 0));
+            }
+        }
+
+        task_lists
+    }
+
+    /// Counts how long the creep has gone without a task and returns a task list that recycles it
+    /// once it's been idle for too long, as long as its home room has more creeps of the same role
+    /// than its spawn goals want
+    fn get_recycle_task_list(&mut self, creep: &Creep) -> Option<TaskList> {
+        let creep_id = creep.try_id()?;
+        let idle_ticks = self.idle_ticks.entry(creep_id).or_insert(0);
+        *idle_ticks += 1;
+        if *idle_ticks < crate::config::IDLE_TICKS_BEFORE_RECYCLING {
+            return None;
+        }
+
+        let room = creep.room()?;
+        let creep_type = get_creep_type(creep);
+        let home_room = utils::get_creep_home_room(creep).unwrap_or(room.name());
+        let (target_count, is_global) = crate::spawn::get_role_target_count(home_room, &creep_type);
+        let role_count = game::creeps()
+            .values()
+            .filter(|c| get_creep_type(c) == creep_type)
+            .filter(|c| {
+                is_global
+                    || utils::get_creep_home_room(c).or_else(|| c.room().map(|r| r.name()))
+                        == Some(home_room)
+            })
+            .count() as u32;
+        if role_count <= target_count {
+            return None;
+        }
+
+        let room_info = self.room_info_map.get(&room.name())?;
+        let spawn = room_info.my_spawns.first()?;
+
+        info!(
+            "{} has been idle for {} ticks, recycling it",
+            creep.name(),
+            idle_ticks
+        );
+        let task = Box::new(RecycleTask::new(spawn.id()));
+        Some(TaskList::new(vec![task], false, 0))
+    }
+
+    /// Returns the next task list for a creep that was spawned for a room being evacuated. Creeps
+    /// that can carry take whatever is left in the room's storage and terminal to the nearest
+    /// other owned room with a storage. Everything else, and every creep once the room is empty,
+    /// is recycled.
+    fn get_room_evacuation_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let current_room = creep.room()?.name();
+        // creeps spawned before home rooms were encoded belong to the room they're in
+        let evacuating_room = utils::get_creep_home_room(creep).unwrap_or(current_room);
+        if !utils::is_room_evacuating(evacuating_room) {
+            return None;
+        }
+
+        let destination = self
+            .room_info_map
+            .iter()
+            .filter(|(room_name, _)| !utils::is_room_evacuating(**room_name))
+            .filter_map(|(room_name, room_info)| {
+                let storage = room_info.room.storage().filter(|s| s.my())?;
+                let distance =
+                    game::map::get_room_linear_distance(evacuating_room, *room_name, false);
+                Some((distance, storage))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, storage)| storage);
+
+        let can_carry = creep.body().iter().any(|p| p.part() == Part::Carry);
+        if let (true, Some(destination)) = (can_carry, destination.as_ref()) {
+            // drop off anything the creep is already carrying
+            if let Some(resource) = creep.store().store_types().first() {
+                let task = Box::new(TransferTask::with_resource(destination.id(), *resource));
+                return Some(TaskList::new(vec![task], false, 0));
+            }
+
+            if let Some(room) = game::rooms().get(evacuating_room) {
+                let storage = room.storage().map(StructureObject::StructureStorage);
+                let terminal = room.terminal().map(StructureObject::StructureTerminal);
+                for structure in [storage, terminal].into_iter().flatten() {
+                    let store = structure.as_has_store().unwrap().store();
+                    let resource = store
+                        .store_types()
+                        .into_iter()
+                        .max_by_key(|resource| store.get_used_capacity(Some(*resource)));
+                    if resource.is_none() {
+                        continue;
+                    }
+                    let resource = resource.unwrap();
+
+                    let withdraw_task: Box<dyn Task> = match &structure {
+                        StructureObject::StructureStorage(s) => {
+                            Box::new(WithdrawTask::with_resource(s.id(), resource))
+                        }
+                        StructureObject::StructureTerminal(s) => {
+                            Box::new(WithdrawTask::with_resource(s.id(), resource))
+                        }
+                        _ => continue,
+                    };
+                    let transfer_task =
+                        Box::new(TransferTask::with_resource(destination.id(), resource));
+                    return Some(TaskList::new(vec![withdraw_task, transfer_task], false, 1));
+                }
+            }
+        }
+
+        let spawn_room = if self
+            .room_info_map
+            .get(&current_room)
+            .map(|room_info| !room_info.my_spawns.is_empty())
+            .unwrap_or(false)
+        {
+            current_room
+        } else {
+            destination?.room()?.name()
+        };
+        let spawn = self.room_info_map.get(&spawn_room)?.my_spawns.first()?;
+
+        info!(
+            "{} is done evacuating {}, recycling it",
+            creep.name(),
+            evacuating_room
+        );
+        let task = Box::new(RecycleTask::new(spawn.id()));
+        Some(TaskList::new(vec![task], false, 0))
+    }
+
+    /// Returns a task list renewing an idle creep at a nearby spawn, as long as the spawn isn't
+    /// busy and the room's energy is full. Creeps wait for work next to the spawn instead of
+    /// ageing while they have nothing to do.
+    fn get_renew_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        if creep.ticks_to_live()? >= crate::config::RENEW_BELOW_TICKS_TO_LIVE {
+            return None;
+        }
+
+        // creeps with claim parts can't be renewed
+        if creep.body().iter().any(|p| p.part() == Part::Claim) {
+            return None;
+        }
+
+        let room = creep.room()?;
+        if room.energy_available() < room.energy_capacity_available() {
+            return None;
+        }
+
+        let room_info = self.room_info_map.get(&room.name())?;
+        let spawn = room_info
+            .my_spawns
+            .iter()
+            .filter(|spawn| spawn.spawning().is_none())
+            .filter(|spawn| {
+                creep
+                    .pos()
+                    .in_range_to(spawn.pos(), crate::config::RENEW_RANGE)
+            })
+            .min_by_key(|spawn| creep.pos().get_range_to(spawn.pos()))?;
+
+        let task = Box::new(RenewTask::new(spawn.id()));
+        Some(TaskList::new(vec![task], false, 0))
+    }
+
     fn get_idle_creeps(&self) -> Vec<Creep> {
         let creeps = game::creeps().values();
         let mut idle_creeps: Vec<Creep> = Vec::new();
@@ -857,77 +1726,200 @@ impl TaskManager {
         pos: &Position,
         target_count: u32,
     ) -> bool {
+        self.get_working_creep_count(room_name, pos) >= target_count
+    }
+
+    fn get_working_creep_count(&self, room_name: &RoomName, pos: &Position) -> u32 {
         if let Some(room) = self.working_creeps_by_room_and_pos.get(room_name) {
             if let Some(count) = room.get(pos) {
-                return *count >= target_count;
+                return *count;
             }
         }
-        false
+        0
     }
 
     fn get_default_task_list_for_creep(&self, creep: &Creep) -> Option<TaskList> {
         let creep_type = get_creep_type(creep);
         let creep_parts = creep.body().iter().map(|p| p.part()).collect::<Vec<Part>>();
+        // creeps on an exit tile can briefly be without a room
+        let room = creep.room()?;
+
+        // specialized creeps can only do their job in the room they were spawned for
+        if [
+            "source_harvester",
+            "upgrader",
+            "storager",
+            "mineral_harvester",
+            "mineral_courier",
+        ]
+        .contains(&creep_type.as_str())
+        {
+            if let Some(task) = get_return_to_home_room_task(creep) {
+                return Some(TaskList::new(vec![task], false, 0));
+            }
+        }
 
         if creep_type == "source_harvester" {
-            return self.get_harvest_source_task_list(creep, false, true);
+            return self
+                .get_harvest_source_task_list(creep, false, true)
+                .or_else(|| self.get_drop_harvest_task_list(creep));
         } else if creep_type == "upgrader" {
-            let ControllerLink(structure_link, controller) = self
-                .room_info_map
-                .get(&creep.room().unwrap().name())
-                .unwrap()
+            let room_info = self.room_info_map.get(&room.name())?;
+            let controller = room_info.controller.as_ref()?;
+            let controller_link = room_info
                 .links
-                .controller_links
-                .get(0)
-                .unwrap();
+                .first_controller_link()
+                .map(|ControllerLink(link, _)| link);
 
             let upgrade_task = Box::new(UpgradeTask::new(controller.id()));
-            let withdraw_task = Box::new(WithdrawTask::new(structure_link.id()));
-            let idle_until_task = Box::new(IdleUntilTask::new(
-                |_, link: &ObjectId<StructureLink>| {
-                    link.resolve()
-                        .unwrap()
-                        .store()
-                        .get_used_capacity(Some(ResourceType::Energy))
-                        > 0
-                },
-                structure_link.id(),
-            ));
-            return Some(TaskList::new(
-                vec![withdraw_task, upgrade_task, idle_until_task],
-                true,
-                1,
-            ));
-        } else if creep_type == "storager" {
-            if let Some(StorageLink(storage_link, storage)) = self
-                .room_info_map
-                .get(&creep.room().unwrap().name())
-                .unwrap()
-                .links
-                .storage_links
-                .get(0)
-            {
-                let withdraw_task = Box::new(WithdrawTask::new(storage_link.id()));
-                let transfer_task = Box::new(TransferTask::new(storage.id()));
-                let idle_until_task = Box::new(IdleUntilTask::new(
-                    |_, link: &ObjectId<StructureLink>| {
-                        link.resolve()
-                            .unwrap()
-                            .store()
-                            .get_used_capacity(Some(ResourceType::Energy))
-                            > 0
-                    },
-                    storage_link.id(),
-                ));
-                return Some(TaskList::new(
-                    vec![withdraw_task, transfer_task, idle_until_task],
-                    true,
+            if utils::energy(creep) > 0 {
+                return Some(TaskList::new(vec![upgrade_task], false, 0));
+            }
+
+            if let Some(controller_link) = controller_link {
+                if controller_link
+                    .store()
+                    .get_used_capacity(Some(ResourceType::Energy))
+                    > 0
+                {
+                    let withdraw_task =
+                        Box::new(WithdrawTask::new(controller_link.id()).queue_when_crowded());
+                    return Some(TaskList::new(vec![withdraw_task, upgrade_task], false, 1));
+                }
+            }
+
+            let withdraw_task = get_nearest_withdraw_task(controller.pos(), &room_info.room, true);
+
+            // upgraders without a link share the controller container
+            if controller_link.is_none() {
+                return Some(TaskList::new(vec![withdraw_task?, upgrade_task], false, 1));
+            }
+            let controller_link = controller_link.unwrap();
+
+            // linked upgraders wait for the link to refill, and only fall back to storage or a
+            // container if nothing is feeding it
+            let mut idle_until_task = IdleUntilTask::has_energy(controller_link.id())
+                .with_timeout(crate::config::UPGRADER_LINK_WAIT_TICKS);
+            if let Some(withdraw_task) = withdraw_task {
+                idle_until_task = idle_until_task.then_on_timeout(TaskList::new(
+                    vec![withdraw_task, upgrade_task],
+                    false,
                     1,
                 ));
             }
+            return Some(TaskList::new(vec![Box::new(idle_until_task)], false, 0));
+        } else if creep_type == "storager" {
+            let room_info = self.room_info_map.get(&room.name())?;
+            if let Some(StorageLink(storage_link, storage)) = room_info.links.first_storage_link() {
+                let storage_full = storage
+                    .store()
+                    .get_free_capacity(Some(ResourceType::Energy))
+                    <= 0;
+
+                // a full storage can't take what the storager is holding, so it goes wherever
+                // else it's useful
+                if utils::energy(creep) > 0 {
+                    if storage_full {
+                        return self.get_energy_dump_task_list(creep);
+                    }
+                    let transfer_task = Box::new(TransferTask::new(storage.id()));
+                    return Some(TaskList::new(vec![transfer_task], false, 0));
+                }
+
+                if storage_link
+                    .store()
+                    .get_used_capacity(Some(ResourceType::Energy))
+                    > 0
+                {
+                    let transfer_task: Box<dyn Task> = if storage_full {
+                        let terminal = room_info.my_structures.iter().find_map(|s| {
+                            if let StructureObject::StructureTerminal(terminal) = s {
+                                if terminal
+                                    .store()
+                                    .get_free_capacity(Some(ResourceType::Energy))
+                                    > 0
+                                {
+                                    return Some(terminal);
+                                }
+                            }
+                            None
+                        })?;
+                        Box::new(TransferTask::new(terminal.id()))
+                    } else {
+                        Box::new(TransferTask::new(storage.id()))
+                    };
+                    let withdraw_task =
+                        Box::new(WithdrawTask::new(storage_link.id()).queue_when_crowded());
+                    return Some(TaskList::new(vec![withdraw_task, transfer_task], false, 1));
+                }
+
+                let idle_until_task = Box::new(IdleUntilTask::has_energy(storage_link.id()));
+                return Some(TaskList::new(vec![idle_until_task], false, 0));
+            }
+        }
+
+        if creep_type == "mineral_harvester" {
+            return self.get_mineral_harvest_task_list(creep);
+        }
+
+        if creep_type == "mineral_courier" {
+            return self.get_mineral_courier_task_list(creep);
+        }
+
+        // core attackers head for the invader core closest to home, and attack it once there
+        if creep_type == "core_attacker" {
+            let home_room = utils::get_creep_home_room(creep).unwrap_or(room.name());
+            let core_room = crate::intel::get_invader_core_rooms(&self.room_info_map)
+                .into_iter()
+                .min_by_key(|core_room| {
+                    game::map::get_room_linear_distance(home_room, *core_room, false)
+                })?;
+            if core_room == room.name() {
+                return None;
+            }
+            let task = Box::new(TravelDumbTask::new(
+                RoomPosition::new(25, 25, core_room).into(),
+            ));
+            return Some(TaskList::new(vec![task], false, 0));
+        }
+
+        // scouts visit the stalest room next to home, refreshing its intel when they arrive
+        if creep_type == "scout" {
+            let home_room = utils::get_creep_home_room(creep).unwrap_or(room.name());
+            let neighbour = *crate::intel::get_stale_neighbours(home_room).first()?;
+            let task = Box::new(TravelDumbTask::new(
+                RoomPosition::new(25, 25, neighbour).into(),
+            ));
+            return Some(TaskList::new(vec![task], false, 0));
         }
 
         if creep_parts.contains(&Part::Attack) {
+            if creep_type == "melee" {
+                if let Some(attack_flag) = game::flags()
+                    .values()
+                    .find(|f| f.name().starts_with("attack", 0))
+                {
+                    // the squad waits at home until it's strong enough for the room's defences.
+                    // Only the melee creeps waiting here count, not defenders elsewhere or creeps
+                    // already on their way
+                    let attack_power = room
+                        .find(find::MY_CREEPS, None)
+                        .iter()
+                        .filter(|c| get_creep_type(c) == "melee")
+                        .map(crate::metadata::get_attack_power)
+                        .sum();
+                    if attack_flag.pos().room_name() != room.name()
+                        && crate::intel::is_siege_squad_ready(
+                            attack_flag.pos().room_name(),
+                            attack_power,
+                        )
+                    {
+                        let task = Box::new(TravelDumbTask::new(attack_flag.pos()));
+                        return Some(TaskList::new(vec![task], false, 0));
+                    }
+                }
+            }
+
             if let Some(defend_flag) = game::flags().values().find(|f| f.name() == "defend") {
                 if !creep.pos().in_range_to(defend_flag.pos(), 3) {
                     let task = Box::new(TravelDumbTask::new(defend_flag.pos()));
@@ -937,21 +1929,33 @@ impl TaskManager {
                 }
             }
 
-            let controller = creep.room().unwrap().controller().unwrap();
-            if !creep.pos().in_range_to(controller.pos(), 3) {
-                let task = Box::new(TravelTask::new(controller.id()));
-                return Some(TaskList::new(vec![task], false, 0));
+            if let Some(controller) = room.controller() {
+                if !creep.pos().in_range_to(controller.pos(), 3) {
+                    let task = Box::new(TravelTask::new(controller.id()));
+                    return Some(TaskList::new(vec![task], false, 0));
+                }
             }
         } else if creep_parts.contains(&Part::Claim) {
             return None;
+        } else if creep_type == "hauler" {
+            if utils::energy(creep) > 0 {
+                return self.get_energy_dump_task_list(creep);
+            }
+            return self.get_container_haul_task_list(creep);
+        } else if creep_type == "builder" {
+            // builders never fall back to upgrading, they wait for the next construction site
+            if utils::energy(creep) > 0 {
+                return None;
+            }
+
+            return self.get_harvest_source_task_list(creep, true, false);
         } else if creep_parts.contains(&Part::Work) {
-            if creep.store().get_used_capacity(Some(ResourceType::Energy)) > 0 {
-                let controller = creep.room().unwrap().controller().unwrap();
-                if controller.my() {
-                    let task = Box::new(UpgradeTask::new(
-                        creep.room().unwrap().controller().unwrap().id(),
-                    ));
-                    return Some(TaskList::new(vec![task], false, 0));
+            if utils::energy(creep) > 0 {
+                if let Some(controller) = room.controller() {
+                    if controller.my() {
+                        let task = Box::new(UpgradeTask::new(controller.id()));
+                        return Some(TaskList::new(vec![task], false, 0));
+                    }
                 }
                 return self.get_harvest_source_task_list(creep, true, false);
             }
@@ -959,7 +1963,7 @@ impl TaskManager {
             return self.get_harvest_source_task_list(creep, true, false);
         }
 
-        if !utils::is_mine(&creep.room().unwrap()) {
+        if !utils::is_mine(&room) {
             if let Some(task) = get_travel_home_task(creep) {
                 return Some(TaskList::new(vec![task], false, 0));
             }
@@ -968,6 +1972,248 @@ impl TaskManager {
         None
     }
 
+    /// Returns a task list boosting each of the creep's unboosted combat parts, if a lab in the
+    /// creep's room is stocked with the configured boost
+    fn get_boost_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let room_info = self.room_info_map.get(&creep.room()?.name())?;
+        if !room_info.is_feature_enabled(Feature::Labs) {
+            return None;
+        }
+
+        let body = creep.body();
+
+        let mut tasks: Vec<Box<dyn Task>> = Vec::new();
+        for (part, resource) in crate::config::COMBAT_BOOSTS.iter() {
+            let unboosted_count = body
+                .iter()
+                .filter(|p| p.part() == *part && p.boost().is_none())
+                .count() as u32;
+            if unboosted_count == 0 {
+                continue;
+            }
+
+            let lab = room_info.my_structures.iter().find_map(|s| {
+                if let StructureObject::StructureLab(lab) = s {
+                    if lab.mineral_type() == Some(*resource)
+                        && lab.store().get_used_capacity(Some(*resource))
+                            >= unboosted_count * LAB_BOOST_MINERAL
+                        && lab.store().get_used_capacity(Some(ResourceType::Energy))
+                            >= unboosted_count * LAB_BOOST_ENERGY
+                    {
+                        return Some(lab);
+                    }
+                }
+                None
+            });
+
+            if let Some(lab) = lab {
+                tasks.push(Box::new(BoostTask::new(lab.id(), *part, *resource)));
+            }
+        }
+
+        if tasks.is_empty() {
+            return None;
+        }
+
+        Some(TaskList::new(tasks, false, 0))
+    }
+
+    /// Returns a task list that carries energy from the fullest source container to storage. Both
+    /// legs follow the same cached route since haulers make this trip over and over.
+    fn get_container_haul_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let room = creep.room()?;
+        let room_info = self.room_info_map.get(&room.name())?;
+
+        let storage = room_info.my_structures.iter().find_map(|s| {
+            if let StructureObject::StructureStorage(storage) = s {
+                return Some(storage);
+            }
+            None
+        })?;
+
+        let sources = room.find(find::SOURCES, None);
+        let container = room_info
+            .structures
+            .iter()
+            .filter_map(|s| {
+                if let StructureObject::StructureContainer(container) = s {
+                    if container
+                        .store()
+                        .get_used_capacity(Some(ResourceType::Energy))
+                        > 0
+                        && sources
+                            .iter()
+                            .any(|source| source.pos().is_near_to(container.pos()))
+                    {
+                        return Some(container);
+                    }
+                }
+                None
+            })
+            .max_by_key(|c| c.store().get_used_capacity(Some(ResourceType::Energy)))?;
+
+        let withdraw_task = WithdrawTask::new(container.id()).via_route(storage.pos());
+        let transfer_task = TransferTask::new(storage.id()).via_route(container.pos());
+        Some(TaskList::new(
+            vec![Box::new(withdraw_task), Box::new(transfer_task)],
+            false,
+            0,
+        ))
+    }
+
+    /// Returns a task list that gets rid of the creep's energy when nothing needs it, trying
+    /// storage, then the terminal, then the controller
+    fn get_energy_dump_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let room_info = self.room_info_map.get(&creep.room()?.name())?;
+
+        for structure in room_info.my_structures.iter() {
+            if let StructureObject::StructureStorage(storage) = structure {
+                if storage
+                    .store()
+                    .get_free_capacity(Some(ResourceType::Energy))
+                    > 0
+                {
+                    let task = Box::new(TransferTask::new(storage.id()));
+                    return Some(TaskList::new(vec![task], false, 0));
+                }
+            }
+        }
+
+        for structure in room_info.my_structures.iter() {
+            if let StructureObject::StructureTerminal(terminal) = structure {
+                if terminal
+                    .store()
+                    .get_free_capacity(Some(ResourceType::Energy))
+                    > 0
+                {
+                    let task = Box::new(TransferTask::new(terminal.id()));
+                    return Some(TaskList::new(vec![task], false, 0));
+                }
+            }
+        }
+
+        let controller = room_info.controller.as_ref()?;
+        if !controller.my() {
+            return None;
+        }
+
+        if creep.body().iter().any(|p| p.part() == Part::Work) {
+            let task = Box::new(UpgradeTask::new(controller.id()));
+            return Some(TaskList::new(vec![task], false, 0));
+        }
+
+        // leave the energy next to the controller for upgraders to pick up
+        let travel_task = Box::new(TravelTask::new(controller.id()));
+        let drop_task = Box::new(DropTask::new(ResourceType::Energy));
+        Some(TaskList::new(vec![travel_task, drop_task], false, 1))
+    }
+
+    /// Returns a task list harvesting the room's mineral from on top of its container. Once the
+    /// deposit is depleted the harvester is recycled, and no more are spawned until it regenerates.
+    fn get_mineral_harvest_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let room_info = self.room_info_map.get(&creep.room()?.name())?;
+        let mineral = room_info.get_extracted_mineral()?;
+
+        if mineral.mineral_amount() == 0 {
+            let spawn = room_info.my_spawns.first()?;
+            info!(
+                "mineral in {} is depleted for {} ticks, recycling {}",
+                mineral.pos().room_name(),
+                mineral.ticks_to_regeneration().unwrap_or(0),
+                creep.name()
+            );
+            let task = Box::new(RecycleTask::new(spawn.id()));
+            return Some(TaskList::new(vec![task], false, 0));
+        }
+
+        let container = *room_info.get_mineral_containers().first()?;
+        let task = Box::new(HarvestMineralTask::new(mineral.id()).on_container(container.pos()));
+        Some(TaskList::new(vec![task], false, 0))
+    }
+
+    /// Returns a task list carrying minerals from the containers by the room's mineral to the
+    /// terminal. Couriers only ever carry minerals, energy is left for the haulers.
+    fn get_mineral_courier_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let room_info = self.room_info_map.get(&creep.room()?.name())?;
+        let terminal = room_info.my_structures.iter().find_map(|s| {
+            if let StructureObject::StructureTerminal(terminal) = s {
+                return Some(terminal);
+            }
+            None
+        })?;
+
+        if let Some(resource) = creep
+            .store()
+            .store_types()
+            .into_iter()
+            .find(|r| *r != ResourceType::Energy)
+        {
+            if terminal.store().get_free_capacity(Some(resource)) <= 0 {
+                return None;
+            }
+            let transfer_task = Box::new(TransferTask::with_resource(terminal.id(), resource));
+            return Some(TaskList::new(vec![transfer_task], false, 0));
+        }
+
+        let (container, resource) = room_info
+            .get_mineral_containers()
+            .into_iter()
+            .filter_map(|container| {
+                let resource = container
+                    .store()
+                    .store_types()
+                    .into_iter()
+                    .filter(|r| *r != ResourceType::Energy)
+                    .max_by_key(|r| container.store().get_used_capacity(Some(*r)))?;
+                Some((container, resource))
+            })
+            .max_by_key(|(container, resource)| {
+                container.store().get_used_capacity(Some(*resource))
+            })?;
+
+        let withdraw_task = Box::new(
+            WithdrawTask::with_resource(container.id(), resource).via_route(terminal.pos()),
+        );
+        let transfer_task = Box::new(
+            TransferTask::with_resource(terminal.id(), resource).via_route(container.pos()),
+        );
+        Some(TaskList::new(vec![withdraw_task, transfer_task], false, 0))
+    }
+
+    /// Returns a task list that harvests a source with no link or container and drops the energy
+    /// on the ground for haulers to pick up, so the harvester never has to leave the source
+    fn get_drop_harvest_task_list(&self, creep: &Creep) -> Option<TaskList> {
+        let room = creep.room()?;
+        let room_info = self.room_info_map.get(&room.name())?;
+        let mut source_infos: Vec<(Source, super::metadata::SourceInfo)> = room
+            .find(find::SOURCES, None)
+            .into_iter()
+            .map(|s| {
+                let source_info = super::metadata::SourceInfo::new(&s, Some(creep));
+                (s, source_info)
+            })
+            .filter(|(s, source_info)| {
+                !source_info.has_link
+                    && !source_info.has_container
+                    && !room_info.is_guarded_by_keeper(s.pos())
+            })
+            .collect();
+        source_infos.sort_by_key(|(s, source_info)| {
+            (
+                source_info.nearby_source_harvester_count,
+                creep.pos().get_range_to(s.pos()),
+            )
+        });
+        let mut sources: Vec<Source> = source_infos.into_iter().map(|(s, _)| s).collect();
+
+        keep_assigned_source(creep, &mut sources);
+
+        let source = sources.first()?;
+        let harvest_task = Box::new(HarvestSourceTask::new(source.id()));
+        let drop_task = Box::new(DropTask::new(ResourceType::Energy));
+        Some(TaskList::new(vec![harvest_task, drop_task], true, 0))
+    }
+
     fn get_harvest_source_task_list(
         &self,
         creep: &Creep,
@@ -975,18 +2221,13 @@ impl TaskManager {
         link_required: bool,
     ) -> Option<TaskList> {
         // Gather energy
-        let room = creep.room().unwrap();
+        let room = creep.room()?;
         if let Some(controller) = room.controller() {
             if controller.my() {
                 let mut sources: Vec<Source>;
 
                 if link_required {
-                    let links = &self
-                        .room_info_map
-                        .get(&room.name())
-                        .unwrap()
-                        .links
-                        .source_links;
+                    let links = &self.room_info_map.get(&room.name())?.links.source_links;
                     sources = links
                         .iter()
                         .map(|SourceLink(_, source)| source.clone())
@@ -997,6 +2238,11 @@ impl TaskManager {
                     sources = room.find(find::SOURCES, None);
                 }
 
+                // harvesting next to a source keeper is suicide
+                if let Some(room_info) = self.room_info_map.get(&room.name()) {
+                    sources.retain(|s| !room_info.is_guarded_by_keeper(s.pos()));
+                }
+
                 sources.sort_by_key(|s| {
                     let source_info = super::metadata::SourceInfo::new(s, Some(creep));
                     let mut cost = 0;
@@ -1028,43 +2274,27 @@ impl TaskManager {
                     cost
                 });
 
+                keep_assigned_source(creep, &mut sources);
+
+                // every slot is taken, so wait nearby for one to free up
                 if let Some(source) = sources.first() {
-                    let harvest_task = Box::new(HarvestSourceTask::new(source.id()));
+                    let source_info = super::metadata::SourceInfo::new(source, Some(creep));
+                    if source_info.nearby_creep_count >= source_info.non_wall_terrain_count {
+                        return self.get_source_queue_task_list(creep, &room);
+                    }
+                }
+
+                if let Some(source) = sources.first() {
+                    let mut harvest_task = HarvestSourceTask::new(source.id());
 
-                    // transfer to closest source link
+                    // source harvesters empty into the source link whenever they fill up
                     if link_required {
-                        if let Some(SourceLink(source_link, source)) = self
-                            .room_info_map
-                            .get(&room.name())
-                            .unwrap()
-                            .links
-                            .source_links
-                            .iter()
-                            .filter(|SourceLink(_link, tmp_source)| {
-                                tmp_source.pos() == source.pos()
-                            })
-                            .last()
-                        {
-                            let harvest_task = Box::new(HarvestSourceTask::new(source.id()));
-                            let transfer_task = Box::new(TransferTask::new(source_link.id()));
-                            let idle_until_task = Box::new(IdleUntilTask::new(
-                                |_, source: &ObjectId<Source>| {
-                                    source.resolve().unwrap().energy() > 0
-                                },
-                                source.try_id().unwrap(),
-                            ));
-                            return Some(TaskList::new(
-                                vec![harvest_task, transfer_task, idle_until_task],
-                                true,
-                                1,
-                            ));
-                        }
+                        harvest_task = harvest_task.with_link_transfer();
                     }
-                    return Some(TaskList::new(vec![harvest_task], false, 0));
+                    return Some(TaskList::new(vec![Box::new(harvest_task)], false, 0));
                 } else {
-                    // There are no sources to gather from and the creep has no energy
-                    // so do nothing
-                    return None;
+                    // There are no sources to gather from right now, so wait by the least busy one
+                    return self.get_source_queue_task_list(creep, &room);
                 }
             } else {
                 // Go back to an owned room if we can't harvest in the current room
@@ -1076,53 +2306,251 @@ impl TaskManager {
 
         None
     }
+
+    /// Parks the creep a few tiles from the least busy source in the room and waits there until
+    /// the source has a free slot and energy, so it can take over as soon as a harvester leaves
+    fn get_source_queue_task_list(&self, creep: &Creep, room: &Room) -> Option<TaskList> {
+        let room_info = self.room_info_map.get(&room.name())?;
+        let source = room
+            .find(find::SOURCES, None)
+            .into_iter()
+            .filter(|s| !room_info.is_guarded_by_keeper(s.pos()))
+            .min_by_key(|s| {
+                let source_info = super::metadata::SourceInfo::new(s, Some(creep));
+                (
+                    source_info.nearby_creep_count as i32
+                        - source_info.non_wall_terrain_count as i32,
+                    creep.pos().get_range_to(s.pos()),
+                )
+            })?;
+
+        let range = crate::config::SOURCE_QUEUE_RANGE;
+        let idle_until_task = Box::new(IdleUntilTask::source_has_free_slot(source.id()));
+        if creep.pos().in_range_to(source.pos(), range as u32) {
+            return Some(TaskList::new(vec![idle_until_task], false, 0));
+        }
+
+        // the closest walkable tile at the queue range
+        let terrain = room.get_terrain();
+        let x = source.pos().x().u8() as i32;
+        let y = source.pos().y().u8() as i32;
+        let range = range as i32;
+        let parking_pos = (-range..=range)
+            .flat_map(|dx| (-range..=range).map(move |dy| (x + dx, y + dy)))
+            .filter(|(px, py)| {
+                (px - x).abs().max((py - y).abs()) == range
+                    && (1..49).contains(px)
+                    && (1..49).contains(py)
+                    && terrain.get(*px as u8, *py as u8) != screeps::Terrain::Wall
+            })
+            .map(|(px, py)| RoomPosition::new(px as u8, py as u8, room.name()).pos())
+            .min_by_key(|pos| creep.pos().get_range_to(*pos))?;
+
+        let travel_task = Box::new(TravelDumbTask::new(parking_pos));
+        Some(TaskList::new(vec![travel_task, idle_until_task], false, 0))
+    }
 }
 
 fn get_travel_home_task(creep: &Creep) -> Option<Box<dyn Task>> {
-    let rooms = screeps::game::rooms().values();
-    let mut my_owned_rooms = rooms
-        .filter(|room| room.controller().map(|c| c.my()).unwrap_or(false))
-        .collect::<Vec<_>>();
+    // rooms without a controller can't be ours, so they're skipped rather than unwrapped
+    let my_controllers = screeps::game::rooms()
+        .values()
+        .filter_map(|room| room.controller())
+        .filter(|controller| controller.my());
 
-    // Sort rooms by distance to creep (closest first)
+    // Head to the closest owned room
     // TODO: this is probably not the best way to do this but it works for now
-    my_owned_rooms.sort_by(|a, b| {
+    let controller =
+        my_controllers.min_by_key(|controller| creep.pos().get_range_to(controller.pos()))?;
+    Some(Box::new(TravelTask::new(controller.id())))
+}
+
+/// Source harvesters stick to the first source they're given for their whole life, instead of
+/// swapping sources whenever another one looks cheaper. Moves the assigned source to the front of
+/// the candidates, or assigns the best candidate if the creep has no source or its source is no
+/// longer one of them.
+fn keep_assigned_source(creep: &Creep, sources: &mut Vec<Source>) {
+    if get_creep_type(creep) != "source_harvester" {
+        return;
+    }
+
+    if let Some(source_pos) = utils::get_memory_position(creep, "source_pos") {
+        if let Some(index) = sources.iter().position(|s| s.pos() == source_pos) {
+            let source = sources.remove(index);
+            sources.insert(0, source);
+            return;
+        }
+    }
+
+    if let Some(source) = sources.first() {
+        info!("{} is assigned to source at {}", creep.name(), source.pos());
+        utils::set_memory_position(creep, "source_pos", source.pos());
+    }
+}
+
+/// Repairs a damaged road or container next to the creep, if the creep has the parts and energy for
+/// it. Keeps roads along busy paths topped up without dedicated repair trips.
+fn repair_in_passing(creep: &Creep) {
+    if utils::is_empty(creep) || !creep.body().iter().any(|p| p.part() == Part::Work) {
+        return;
+    }
+
+    let damaged = creep
+        .pos()
+        .find_in_range(find::STRUCTURES, 1)
+        .into_iter()
+        .find(|s| {
+            matches!(
+                s.structure_type(),
+                StructureType::Road | StructureType::Container
+            ) && (s.as_structure().hits() as f32)
+                < s.as_structure().hits_max() as f32 * crate::config::PASSING_REPAIR_HITS_RATIO
+        });
+
+    if let Some(structure) = damaged {
         creep
-            .pos()
-            .get_range_to(a.controller().unwrap().pos())
-            .cmp(&creep.pos().get_range_to(b.controller().unwrap().pos()))
-    });
-
-    if let Some(room) = my_owned_rooms.first() {
-        Some(Box::new(TravelTask::new(room.controller().unwrap().id())))
-    } else {
-        None
+            .repair(structure.as_structure())
+            .unwrap_or_else(|e| debug!("{} couldn't repair in passing: {:?}", creep.name(), e));
+    }
+}
+
+/// Returns a task that takes the creep back to the room it was spawned for, if it has wandered
+/// out of it. Creeps spawned before home rooms were encoded in names head to the nearest owned
+/// room when they're outside of one.
+fn get_return_to_home_room_task(creep: &Creep) -> Option<Box<dyn Task>> {
+    let current_room = creep.room()?;
+    let home_room = match utils::get_creep_home_room(creep) {
+        Some(home_room) => home_room,
+        None if !utils::is_mine(&current_room) => return get_travel_home_task(creep),
+        None => return None,
+    };
+
+    if current_room.name() == home_room {
+        return None;
+    }
+
+    info!("{} is outside its home room {}", creep.name(), home_room);
+    match game::rooms()
+        .get(home_room)
+        .and_then(|room| room.controller())
+    {
+        Some(controller) => Some(Box::new(TravelTask::new(controller.id()))),
+        None => Some(Box::new(TravelDumbTask::new(
+            RoomPosition::new(25, 25, home_room).into(),
+        ))),
     }
 }
 
+/// Returns a task list moving the resource out of a structure that's about to be demolished, into
+/// the closest other store with room for it. Stores of the same type are preferred, so an old
+/// container empties into its replacement.
+fn get_evacuation_task_list(
+    room_info: &RoomInfo,
+    structure: &StructureObject,
+    resource: ResourceType,
+) -> Option<TaskList> {
+    let destination = room_info
+        .structures
+        .iter()
+        .filter(|s| {
+            s.pos() != structure.pos()
+                && matches!(
+                    s,
+                    StructureObject::StructureStorage(_)
+                        | StructureObject::StructureTerminal(_)
+                        | StructureObject::StructureContainer(_)
+                )
+                && s.as_has_store()
+                    .unwrap()
+                    .store()
+                    .get_free_capacity(Some(resource))
+                    > 0
+        })
+        .min_by_key(|s| {
+            (
+                s.structure_type() != structure.structure_type(),
+                s.pos().get_range_to(structure.pos()),
+            )
+        })?;
+
+    let withdraw_task: Box<dyn Task> = match structure {
+        StructureObject::StructureStorage(s) => {
+            Box::new(WithdrawTask::with_resource(s.id(), resource))
+        }
+        StructureObject::StructureTerminal(s) => {
+            Box::new(WithdrawTask::with_resource(s.id(), resource))
+        }
+        StructureObject::StructureContainer(s) => {
+            Box::new(WithdrawTask::with_resource(s.id(), resource))
+        }
+        _ => return None,
+    };
+    let transfer_task: Box<dyn Task> = match destination {
+        StructureObject::StructureStorage(s) => {
+            Box::new(TransferTask::with_resource(s.id(), resource))
+        }
+        StructureObject::StructureTerminal(s) => {
+            Box::new(TransferTask::with_resource(s.id(), resource))
+        }
+        StructureObject::StructureContainer(s) => {
+            Box::new(TransferTask::with_resource(s.id(), resource))
+        }
+        _ => return None,
+    };
+
+    Some(TaskList::new(vec![withdraw_task, transfer_task], false, 0))
+}
+
+/// Puts a withdrawal from the energy store nearest the task's target ahead of the task, so the
+/// creep doesn't cross the room to fill up with whichever store happened to be found first
 fn allow_withdrawal_from_storage(
-    storage: Option<&StructureObject>,
+    energy_stores: &[&StructureObject],
     next_task: Box<dyn Task>,
 ) -> TaskList {
-    let mut tasks = vec![next_task];
-    if let Some(StructureObject::StructureStorage(storage)) = storage {
-        let withdraw_task = Box::new(WithdrawTask::new(storage.id()));
+    let target_pos = next_task.get_target_pos();
+    let energy_store = energy_stores
+        .iter()
+        .min_by_key(|s| target_pos.map(|pos| pos.get_range_to(s.pos())).unwrap_or(0));
 
-        tasks.insert(0, withdraw_task)
+    let mut tasks = vec![next_task];
+    match energy_store {
+        Some(StructureObject::StructureStorage(storage)) => {
+            tasks.insert(0, Box::new(WithdrawTask::new(storage.id())))
+        }
+        Some(StructureObject::StructureContainer(container)) => {
+            tasks.insert(0, Box::new(WithdrawTask::new(container.id())))
+        }
+        _ => {}
     }
 
     let tasks_count = tasks.len() - 1;
     TaskList::new(tasks, false, tasks_count)
 }
 
-fn can_creep_handle_task(creep: &Creep, task: &dyn Task) -> bool {
+fn allow_waiting_at_container(
+    container: &StructureContainer,
+    next_task: Box<dyn Task>,
+) -> TaskList {
+    let idle_until_task = Box::new(
+        IdleUntilTask::creep_or_store_has_energy(container.id())
+            .with_timeout(crate::config::BUILDER_CONTAINER_WAIT_TICKS),
+    );
+    let withdraw_task = Box::new(WithdrawTask::new(container.id()));
+
+    TaskList::new(vec![idle_until_task, withdraw_task, next_task], false, 2)
+}
+
+fn can_creep_handle_task(creep: &Creep, task_list: &TaskList) -> bool {
+    let task = task_list.current_task().unwrap();
     let creep_type = get_creep_type(creep);
     let creep_parts = creep.body().iter().map(|p| p.part()).collect::<Vec<Part>>();
-    let task_parts = task.requires_body_parts();
 
-    for part in task_parts {
-        if !creep_parts.contains(&part) {
-            return false;
+    // every task in the list has to be doable, not just the first one
+    for list_task in task_list.iter() {
+        for part in list_task.requires_body_parts() {
+            if !creep_parts.contains(&part) {
+                return false;
+            }
         }
     }
 
@@ -1132,11 +2560,240 @@ fn can_creep_handle_task(creep: &Creep, task: &dyn Task) -> bool {
         return task.get_type() == TaskType::Upgrade;
     } else if creep_type == "storager" {
         return task.get_type() == TaskType::Withdraw;
+    } else if creep_type == "builder" {
+        return task_list.get_primary_task().unwrap().get_type() == TaskType::Build;
+    } else if creep_type == "core_attacker" {
+        return task.get_type() == TaskType::AttackStructure;
+    } else if creep_type == "keeper_killer" {
+        return task.get_type() == TaskType::KillKeeper;
+    } else if creep_type == "mineral_harvester" {
+        return task.get_type() == TaskType::HarvestMineral;
+    } else if creep_type == "mineral_courier" {
+        // couriers only carry minerals, which they find for themselves
+        return false;
+    } else if creep_type == "scout" {
+        return false;
+    } else if creep_type == "rampart_defender" {
+        return task.get_type() == TaskType::DefendRampart;
     }
 
     true
 }
 
+/// Returns a task to withdraw energy from the storage or container with energy closest to the
+/// position. Containers by the mineral are never used, and neither are controller containers
+/// unless `include_controller_containers` is set, since they're kept stocked for upgraders.
+fn get_nearest_withdraw_task(
+    pos: Position,
+    room: &Room,
+    include_controller_containers: bool,
+) -> Option<Box<dyn Task>> {
+    let controller = room.controller();
+    let minerals = room.find(find::MINERALS, None);
+    let has_energy = |s: &StructureObject| match s {
+        StructureObject::StructureStorage(storage) => {
+            storage
+                .store()
+                .get_used_capacity(Some(ResourceType::Energy))
+                > 0
+        }
+        StructureObject::StructureContainer(container) => {
+            let is_controller_container = controller.as_ref().is_some_and(|controller| {
+                container
+                    .pos()
+                    .in_range_to(controller.pos(), crate::config::CONTROLLER_CONTAINER_RANGE)
+            });
+            let is_mineral_container = minerals
+                .iter()
+                .any(|mineral| container.pos().is_near_to(mineral.pos()));
+
+            (include_controller_containers || !is_controller_container)
+                && !is_mineral_container
+                && container
+                    .store()
+                    .get_used_capacity(Some(ResourceType::Energy))
+                    > 0
+        }
+        _ => false,
+    };
+
+    [StructureType::Storage, StructureType::Container]
+        .into_iter()
+        .filter_map(|structure_type| {
+            utils::nearest_structure_with(pos, room, structure_type, &has_energy)
+        })
+        .min_by_key(|s| pos.get_range_to(s.pos()))
+        .and_then(|s| match s {
+            StructureObject::StructureStorage(storage) => {
+                Some(Box::new(WithdrawTask::new(storage.id())) as Box<dyn Task>)
+            }
+            StructureObject::StructureContainer(container) => {
+                Some(Box::new(WithdrawTask::new(container.id())) as Box<dyn Task>)
+            }
+            _ => None,
+        })
+}
+
+/// Called by work tasks when the creep runs out of energy. Switches the creep to withdrawing from
+/// the nearest storage or container with energy (see `get_nearest_withdraw_task`) and then doing
+/// the same task again, so it comes back to its work instead of being reassigned. Returns false,
+/// leaving the task to complete as usual, if there's nowhere to refuel or the room's spawns are
+/// waiting on energy, since the creep may be needed to fill them. Only workers and builders refuel
+/// this way; other roles have their own energy supply, such as an upgrader's controller link.
+fn refuel_then(
+    creep: &Creep,
+    next_task: Box<dyn Task>,
+    switch: Box<dyn FnOnce(ObjectId<Creep>, TaskList)>,
+) -> bool {
+    if !matches!(get_creep_type(creep).as_str(), "worker" | "builder") {
+        return false;
+    }
+
+    let room = creep.room();
+    if room.is_none() {
+        return false;
+    }
+    let room = room.unwrap();
+
+    if super::ENERGY_STARVED_ROOMS.with(|rooms| rooms.borrow().contains(&room.name())) {
+        return false;
+    }
+
+    let withdraw_task = get_nearest_withdraw_task(creep.pos(), &room, false);
+    match withdraw_task {
+        Some(withdraw_task) => {
+            switch(
+                creep.try_id().unwrap(),
+                TaskList::new(vec![withdraw_task, next_task], false, 1),
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns the room the task lists belong to if it's waiting on energy to spawn a creep
+fn get_energy_starved_room(task_lists: &[TaskList]) -> Option<RoomName> {
+    let room_name = task_lists
+        .first()?
+        .get_primary_task()?
+        .get_target_pos()?
+        .room_name();
+    super::ENERGY_STARVED_ROOMS
+        .with(|rooms| rooms.borrow().contains(&room_name))
+        .then_some(room_name)
+}
+
+/// Returns true if the task list's primary target is one of the room's spawns or extensions
+fn is_spawn_fill(room_info: &RoomInfo, task_list: &TaskList) -> bool {
+    let target_pos = task_list
+        .get_primary_task()
+        .and_then(|task| task.get_target_pos());
+    if target_pos.is_none() {
+        return false;
+    }
+    let target_pos = target_pos.unwrap();
+
+    room_info.my_structures.iter().any(|s| {
+        matches!(
+            s.structure_type(),
+            StructureType::Spawn | StructureType::Extension
+        ) && s.pos() == target_pos
+    })
+}
+
+/// Logs every task list offered to the creep along with whether the creep could take it, for
+/// following a single creep's assignment with `TRACE_ASSIGNMENT_CREEP`
+fn trace_candidate_task_lists(creep: &Creep, task_lists: &[TaskList]) {
+    trace!(
+        "{} choosing from {} task lists",
+        creep.name(),
+        task_lists.len()
+    );
+    for (index, task_list) in task_lists.iter().enumerate() {
+        trace!(
+            "{} [{}] {:?} (can handle: {}, can reach: {})",
+            creep.name(),
+            index,
+            task_list.get_primary_task(),
+            can_creep_handle_task(creep, task_list),
+            can_creep_reach_in_time(creep, task_list)
+        );
+    }
+}
+
+/// Returns the index of the nearest task list with the same primary task type as the creep's last
+/// assignment, as long as it's within `TASK_AFFINITY_RANGE`. This keeps creeps doing the same kind
+/// of work instead of walking off to switch between building and repairing every assignment.
+fn get_affine_task_list_index(creep: &Creep, task_lists: &[TaskList]) -> Option<usize> {
+    let last_task_type =
+        js_sys::Reflect::get(&creep.memory(), &JsValue::from_str("last_task_type"))
+            .ok()?
+            .as_string()?;
+
+    task_lists
+        .iter()
+        .enumerate()
+        .filter_map(|(index, task_list)| {
+            let primary_task = task_list.get_primary_task()?;
+            if format!("{:?}", primary_task.get_type()) != last_task_type {
+                return None;
+            }
+
+            let task = task_list.current_task()?;
+            if task.requires_energy() && utils::is_empty(creep) {
+                return None;
+            }
+
+            if !can_creep_handle_task(creep, task_list)
+                || !can_creep_reach_in_time(creep, task_list)
+            {
+                return None;
+            }
+
+            let distance = creep.pos().get_range_to(primary_task.get_target_pos()?);
+            if distance > crate::config::TASK_AFFINITY_RANGE {
+                return None;
+            }
+
+            Some((index, distance))
+        })
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(index, _)| index)
+}
+
+/// Returns false if the creep would likely die before reaching the task list's primary target
+fn can_creep_reach_in_time(creep: &Creep, task_list: &TaskList) -> bool {
+    let ticks_to_live = match creep.ticks_to_live() {
+        Some(ticks_to_live) => ticks_to_live,
+        None => return true,
+    };
+
+    let target = match task_list
+        .get_primary_task()
+        .and_then(|t| t.get_target_pos())
+    {
+        Some(target) => target,
+        None => return true,
+    };
+
+    // assume a fully loaded creep on plains: each non-move part generates 2 fatigue per tile and
+    // each move part removes 2
+    let move_parts = creep
+        .body()
+        .iter()
+        .filter(|p| p.part() == Part::Move)
+        .count() as u32;
+    let other_parts = creep.body().len() as u32 - move_parts;
+    if move_parts == 0 {
+        return false;
+    }
+    let ticks_per_tile = std::cmp::max(other_parts.div_ceil(move_parts), 1);
+
+    let eta = creep.pos().get_range_to(target) * ticks_per_tile;
+    (eta as f32) <= ticks_to_live as f32 * crate::config::MAX_TRAVEL_TTL_FRACTION
+}
+
 fn update_creep_memory(creep: &Creep, task_list: &TaskList) {
     if let Some(task) = task_list.current_task() {
         info!(
@@ -1150,6 +2807,9 @@ fn update_creep_memory(creep: &Creep, task_list: &TaskList) {
             &JsValue::from_str("task"),
             &JsValue::from_str(&format!("{:?}", task)),
         );
+        if let Some(pos) = task.get_target_pos() {
+            utils::set_memory_position(creep, "target_pos", pos);
+        }
     }
 
     let _ = js_sys::Reflect::set(
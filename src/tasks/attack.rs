@@ -1,15 +1,35 @@
 use std::fmt::Debug;
 
 use log::*;
-use screeps::{Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, SharedCreepProperties};
+use screeps::{
+    find, Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, Position, SharedCreepProperties,
+};
+
+use crate::metadata::get_heal_part_count;
 
 pub struct AttackTask {
     target: ObjectId<Creep>,
+    target_start: Option<Position>,
 }
 
 impl AttackTask {
     pub fn new(target: ObjectId<Creep>) -> AttackTask {
-        AttackTask { target }
+        AttackTask {
+            target,
+            target_start: None,
+        }
+    }
+
+    /// Returns the hostile creep with the most heal parts within `HEALER_FOCUS_RANGE` of the
+    /// creep, if it has more of them than the current target
+    fn get_nearby_healer(&self, creep: &Creep, target_creep: &Creep) -> Option<Creep> {
+        let target_heal_parts = get_heal_part_count(target_creep);
+        creep
+            .pos()
+            .find_in_range(find::HOSTILE_CREEPS, crate::config::HEALER_FOCUS_RANGE)
+            .into_iter()
+            .filter(|c| get_heal_part_count(c) > target_heal_parts)
+            .max_by_key(get_heal_part_count)
     }
 }
 
@@ -22,23 +42,43 @@ impl super::Task for AttackTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         let target_creep = self.target.resolve();
         if target_creep.is_none() {
             info!("cannot attack nonexistent creep");
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
             return;
         }
 
-        let target_creep = target_creep.unwrap();
+        let mut target_creep = target_creep.unwrap();
+
+        // switch to a nearby healer, otherwise it keeps healing whatever is being attacked
+        if let Some(healer) = self.get_nearby_healer(creep, &target_creep) {
+            self.target = healer.try_id().unwrap();
+            self.target_start = None;
+            target_creep = healer;
+        }
+
+        // the creep may travel to the target's room, but doesn't follow it out of that room or
+        // further than the chase range from where it was first seen, where it could be led into
+        // a trap
+        let target_pos = target_creep.pos();
+        let target_start = *self.target_start.get_or_insert(target_pos);
+        let fled_room = target_start.room_name() != target_pos.room_name();
+        let fled_range = !target_start.in_range_to(target_pos, crate::config::MAX_CHASE_RANGE);
+        if fled_room || fled_range {
+            info!("{} stopped chasing {}", creep.name(), target_creep.name());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetFled);
+            return;
+        }
 
         if target_creep.hits() > 0 {
             if creep.pos().is_near_to(target_creep.pos()) {
                 creep.attack(&target_creep).unwrap_or_else(|e| {
                     info!("failed to attack creep: {:?}", e);
-                    cancel(creep.try_id().unwrap());
+                    cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
                 });
             } else {
                 let _ = creep.move_to(&target_creep);
@@ -0,0 +1,115 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, SharedCreepProperties, Structure,
+    StructureType,
+};
+
+/// Which structures a siege goes after first, set by the `attack` flag's name: `attack:spawn`,
+/// `attack:tower` or `attack:all`. Plain `attack` flags take out the towers first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SiegeTarget {
+    Spawn,
+    Tower,
+    All,
+}
+
+impl SiegeTarget {
+    pub fn from_flag_name(flag_name: &str) -> SiegeTarget {
+        match flag_name.split(':').nth(1) {
+            Some("spawn") => SiegeTarget::Spawn,
+            Some("all") => SiegeTarget::All,
+            _ => SiegeTarget::Tower,
+        }
+    }
+
+    /// Returns the order the structure type is attacked in. Lower ranks are attacked first.
+    pub fn rank(&self, structure_type: StructureType) -> u32 {
+        match (self, structure_type) {
+            (SiegeTarget::All, _) => 0,
+            (SiegeTarget::Spawn, StructureType::Spawn) => 0,
+            (SiegeTarget::Tower, StructureType::Tower) => 0,
+            (SiegeTarget::Spawn, StructureType::Tower) => 1,
+            (SiegeTarget::Tower, StructureType::Spawn) => 1,
+            _ => 2,
+        }
+    }
+}
+
+pub struct AttackStructureTask {
+    target: ObjectId<Structure>,
+}
+
+impl AttackStructureTask {
+    pub fn new(target: ObjectId<Structure>) -> AttackStructureTask {
+        AttackStructureTask { target }
+    }
+}
+
+impl super::Task for AttackStructureTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::AttackStructure
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        // the structure no longer resolving means it was destroyed
+        let target_structure = self.target.resolve();
+        if target_structure.is_none() {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        let target_structure = target_structure.unwrap();
+
+        if creep.pos().is_near_to(target_structure.pos()) {
+            creep.attack(&target_structure).unwrap_or_else(|e| {
+                info!("failed to attack structure: {:?}", e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            });
+        } else {
+            let _ = creep.move_to(&target_structure);
+        }
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Attack]
+    }
+
+    fn get_target_pos(&self) -> Option<screeps::Position> {
+        self.target.resolve().map(|target| target.pos())
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("⚔️🏰")
+    }
+}
+
+impl Debug for AttackStructureTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(structure) = self.target.resolve() {
+            write!(
+                f,
+                "Attack {:?} at ({}, {}) in room {} [{}/{}]",
+                structure.structure_type(),
+                structure.pos().x().u8(),
+                structure.pos().y().u8(),
+                structure.pos().room_name(),
+                structure.hits(),
+                structure.hits_max()
+            )
+        } else {
+            write!(f, "Attack structure ({:?})", self.target)
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, ResourceType, SharedCreepProperties,
+    StructureLab,
+};
+
+use crate::metadata::LAB_BOOST_MINERAL;
+
+pub struct BoostTask {
+    target: ObjectId<StructureLab>,
+    part: Part,
+    resource: ResourceType,
+}
+
+impl BoostTask {
+    pub fn new(target: ObjectId<StructureLab>, part: Part, resource: ResourceType) -> BoostTask {
+        BoostTask {
+            target,
+            part,
+            resource,
+        }
+    }
+}
+
+impl super::Task for BoostTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::Boost
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        let all_parts_boosted = creep
+            .body()
+            .iter()
+            .filter(|p| p.part() == self.part)
+            .all(|p| p.boost().is_some());
+        if all_parts_boosted {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        let lab = self.target.resolve();
+        if lab.is_none() {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+
+        let lab = lab.unwrap();
+        if lab.mineral_type() != Some(self.resource)
+            || lab.store().get_used_capacity(Some(self.resource)) < LAB_BOOST_MINERAL
+        {
+            info!("lab no longer has {:?} to boost with", self.resource);
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetEmpty);
+            return;
+        }
+
+        if creep.pos().is_near_to(lab.pos()) {
+            lab.boost_creep(creep, None).unwrap_or_else(|e| {
+                info!("couldn't boost creep: {:?}", e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            });
+        } else {
+            let _ = creep.move_to(&lab);
+        }
+    }
+
+    fn get_target_pos(&self) -> Option<screeps::Position> {
+        self.target.resolve().map(|target| target.pos())
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![self.part]
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("🧪")
+    }
+}
+
+impl Debug for BoostTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(lab) = self.target.resolve() {
+            write!(
+                f,
+                "Boost {:?} with {:?} at ({}, {}) in room {}",
+                self.part,
+                self.resource,
+                lab.pos().x().u8(),
+                lab.pos().y().u8(),
+                lab.pos().room_name()
+            )
+        } else {
+            write!(f, "Boost {:?} ({:?})", self.part, self.target)
+        }
+    }
+}
@@ -2,10 +2,12 @@ use std::fmt::Debug;
 
 use log::*;
 use screeps::{
-    ConstructionSite, Creep, ErrorCode, HasPosition, MaybeHasTypedId, ObjectId, ResourceType,
-    SharedCreepProperties,
+    ConstructionSite, Creep, HasPosition, MaybeHasTypedId, ObjectId, SharedCreepProperties,
 };
 
+use crate::pathing::{approach_then, ApproachError};
+
+#[derive(Clone)]
 pub struct BuildTask {
     target: ObjectId<ConstructionSite>,
 }
@@ -25,26 +27,26 @@ impl super::Task for BuildTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
-        if creep.store().get_used_capacity(Some(ResourceType::Energy)) == 0 {
-            complete(creep.try_id().unwrap());
+        if crate::utils::is_empty(creep) {
+            if !super::refuel_then(creep, Box::new(self.clone()), switch) {
+                complete(creep.try_id().unwrap());
+            }
             return;
         }
 
         if let Some(construction_site) = self.target.resolve() {
-            creep.build(&construction_site).unwrap_or_else(|e| match e {
-                ErrorCode::NotInRange => {
-                    let _ = creep.move_to(&construction_site);
-                }
-                _ => {
-                    info!("couldn't build: {:?}", e);
-                    cancel(creep.try_id().unwrap());
-                }
+            let result = approach_then(creep, construction_site.pos(), 3, || {
+                creep.build(&construction_site)
             });
+            if let Err(ApproachError::Action(e)) = result {
+                info!("couldn't build: {:?}", e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            }
         } else {
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
         }
     }
 
@@ -29,14 +29,24 @@ impl super::Task for ClaimTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         let room_pos = &self.target;
-        let current_room = creep.room().unwrap();
+        let current_room = creep.room();
+        if current_room.is_none() {
+            return;
+        }
+        let current_room = current_room.unwrap();
 
         if current_room.name() == room_pos.room_name() {
-            let controller = current_room.controller().unwrap();
+            let controller = current_room.controller();
+            if controller.is_none() {
+                info!("room {} has no controller to claim", current_room.name());
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+                return;
+            }
+            let controller = controller.unwrap();
             if controller.my() {
                 complete(creep.try_id().unwrap());
                 return;
@@ -52,7 +62,7 @@ impl super::Task for ClaimTask {
                 });
             }
         } else {
-            creep.move_to(room_pos.clone()).unwrap_or_else(|e| {
+            crate::pathing::travel_to(creep, room_pos.clone()).unwrap_or_else(|e| {
                 info!("couldn't move to other room: {:?}", e);
             });
         }
@@ -0,0 +1,102 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    find, Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, Position, SharedCreepProperties,
+    StructureRampart,
+};
+
+/// Stands on a rampart and attacks hostile creeps that come adjacent to it. The creep never steps
+/// off the rampart to chase, so it stays protected for as long as the rampart holds. Completes
+/// once the room is clear of hostiles.
+pub struct DefendRampartTask {
+    target: ObjectId<StructureRampart>,
+}
+
+impl DefendRampartTask {
+    pub fn new(target: ObjectId<StructureRampart>) -> DefendRampartTask {
+        DefendRampartTask { target }
+    }
+}
+
+impl super::Task for DefendRampartTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::DefendRampart
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        let rampart = self.target.resolve();
+        if rampart.is_none() {
+            info!("cannot defend nonexistent rampart");
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+
+        let rampart = rampart.unwrap();
+
+        if creep.pos() != rampart.pos() {
+            let _ = creep.move_to(&rampart);
+            return;
+        }
+
+        let room = creep.room();
+        if room.is_none() {
+            return;
+        }
+        let room = room.unwrap();
+
+        let enemies = room.find(find::HOSTILE_CREEPS, None);
+        if enemies.is_empty() {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        let target = enemies
+            .iter()
+            .filter(|c| creep.pos().is_near_to(c.pos()))
+            .min_by_key(|c| c.hits());
+        if let Some(target) = target {
+            creep.attack(target).unwrap_or_else(|e| {
+                info!("failed to attack creep from rampart: {:?}", e);
+            });
+        }
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Attack]
+    }
+
+    fn get_target_pos(&self) -> Option<Position> {
+        self.target.resolve().map(|rampart| rampart.pos())
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("🛡️")
+    }
+}
+
+impl Debug for DefendRampartTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(rampart) = self.target.resolve() {
+            write!(
+                f,
+                "Defend rampart at ({}, {}) in room {}",
+                rampart.pos().x().u8(),
+                rampart.pos().y().u8(),
+                rampart.pos().room_name()
+            )
+        } else {
+            write!(f, "Defend rampart ({:?})", self.target)
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{Creep, MaybeHasTypedId, ObjectId, Part, ResourceType, SharedCreepProperties};
+
+pub struct DropTask {
+    resource: ResourceType,
+}
+
+impl DropTask {
+    pub fn new(resource: ResourceType) -> DropTask {
+        DropTask { resource }
+    }
+}
+
+impl super::Task for DropTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::Drop
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        if creep.store().get_used_capacity(Some(self.resource)) == 0 {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        match creep.drop(self.resource, None) {
+            Ok(()) => complete(creep.try_id().unwrap()),
+            Err(e) => {
+                info!("couldn't drop {:?}: {:?}", self.resource, e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            }
+        }
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Carry]
+    }
+
+    fn requires_energy(&self) -> bool {
+        self.resource == ResourceType::Energy
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("⬇️")
+    }
+}
+
+impl Debug for DropTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Drop {:?}", self.resource)
+    }
+}
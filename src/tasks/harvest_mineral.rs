@@ -0,0 +1,113 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    Creep, ErrorCode, HasPosition, MaybeHasTypedId, Mineral, ObjectId, Part, Position,
+    SharedCreepProperties,
+};
+
+use crate::pathing::{approach_then, ApproachError};
+
+pub struct HarvestMineralTask {
+    target: ObjectId<Mineral>,
+    container_pos: Option<Position>,
+    move_failure_count: u32,
+}
+
+impl HarvestMineralTask {
+    pub fn new(target: ObjectId<Mineral>) -> HarvestMineralTask {
+        HarvestMineralTask {
+            target,
+            container_pos: None,
+            move_failure_count: 0,
+        }
+    }
+
+    /// The creep harvests from on top of the container so the minerals drop straight into it
+    pub fn on_container(mut self, container_pos: Position) -> HarvestMineralTask {
+        self.container_pos = Some(container_pos);
+        self
+    }
+}
+
+impl super::Task for HarvestMineralTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::HarvestMineral
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        _complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        let mineral = self.target.resolve();
+        if mineral.is_none() {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+        let mineral = mineral.unwrap();
+
+        // the deposit won't have anything until it regenerates
+        if mineral.mineral_amount() == 0 {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetEmpty);
+            return;
+        }
+
+        let (target_pos, range) = match self.container_pos {
+            Some(container_pos) => (container_pos, 0),
+            None => (mineral.pos(), 1),
+        };
+
+        match approach_then(creep, target_pos, range, || creep.harvest(&mineral)) {
+            Ok(_) => self.move_failure_count = 0,
+            // the extractor is cooling down
+            Err(ApproachError::Action(ErrorCode::Tired)) => self.move_failure_count = 0,
+            Err(ApproachError::Action(e)) => {
+                debug!("couldn't harvest mineral: {:?}", e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            }
+            Err(ApproachError::Move(_)) => {
+                self.move_failure_count += 1;
+                if self.move_failure_count >= 3 {
+                    cancel(creep.try_id().unwrap(), super::TaskOutcome::Unreachable);
+                }
+            }
+        }
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Work]
+    }
+
+    fn get_target_pos(&self) -> Option<Position> {
+        self.target.resolve().map(|target| target.pos())
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("⛏️💎")
+    }
+}
+
+impl Debug for HarvestMineralTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(mineral) = self.target.resolve() {
+            write!(
+                f,
+                "Harvest {:?} at ({}, {}) in room {} [{}]",
+                mineral.mineral_type(),
+                mineral.pos().x().u8(),
+                mineral.pos().y().u8(),
+                mineral.pos().room_name(),
+                mineral.mineral_amount()
+            )
+        } else {
+            write!(f, "Harvest mineral ({:?})", self.target)
+        }
+    }
+}
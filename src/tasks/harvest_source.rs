@@ -2,13 +2,16 @@ use std::fmt::Debug;
 
 use log::*;
 use screeps::{
-    Creep, ErrorCode, HasPosition, MaybeHasTypedId, ObjectId, ResourceType, SharedCreepProperties,
-    Source,
+    Creep, HasPosition, HasStore, HasTypedId, MaybeHasTypedId, ObjectId, Part, ResourceType,
+    SharedCreepProperties, Source, StructureLink,
 };
 
+use crate::pathing::{approach_then, ApproachError};
+
 pub struct HarvestSourceTask {
     target: ObjectId<Source>,
     move_failure_count: u32,
+    transfer_to_link: bool,
 }
 
 impl HarvestSourceTask {
@@ -16,8 +19,53 @@ impl HarvestSourceTask {
         HarvestSourceTask {
             target,
             move_failure_count: 0,
+            transfer_to_link: false,
         }
     }
+
+    /// Once full, the creep empties into the source's closest link and goes back to harvesting
+    /// instead of completing
+    pub fn with_link_transfer(mut self) -> HarvestSourceTask {
+        self.transfer_to_link = true;
+        self
+    }
+
+    /// Returns the link closest to the creep that's within range of the source
+    fn get_source_link(&self, creep: &Creep) -> Option<StructureLink> {
+        let room = creep.room()?;
+        super::utils::get_source_links(&room)
+            .iter()
+            .filter(|(_, source_id)| *source_id == self.target)
+            .filter_map(|(link_id, _)| link_id.resolve())
+            .min_by_key(|link| creep.pos().get_range_to(link.pos()))
+    }
+
+    /// Switches to a task list that transfers the harvested energy to the source link and then
+    /// resumes harvesting, or waits if the link is full. Returns false if the source has no link.
+    fn switch_to_link_transfer(
+        &self,
+        creep: &Creep,
+        switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) -> bool {
+        let link = self.get_source_link(creep);
+        if link.is_none() {
+            return false;
+        }
+        let link = link.unwrap();
+
+        // wait for a full link to send its energy on
+        if link.store().get_free_capacity(Some(ResourceType::Energy)) == 0 {
+            return true;
+        }
+
+        let transfer_task = Box::new(super::TransferTask::new(link.id()));
+        let harvest_task = Box::new(HarvestSourceTask::new(self.target).with_link_transfer());
+        switch(
+            creep.try_id().unwrap(),
+            super::TaskList::new(vec![transfer_task, harvest_task], false, 1),
+        );
+        true
+    }
 }
 
 impl super::Task for HarvestSourceTask {
@@ -29,67 +77,57 @@ impl super::Task for HarvestSourceTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         if let Some(ticks_to_live) = creep.ticks_to_live() {
             if ticks_to_live <= 3 {
-                cancel(creep.try_id().unwrap());
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Dying);
                 return;
             }
         }
 
-        let free_capacity = creep.store().get_free_capacity(Some(ResourceType::Energy));
-        if free_capacity == 0 {
-            complete(creep.try_id().unwrap());
-            return;
-        }
-
+        let free_capacity = crate::utils::free_energy(creep);
         if 10 > free_capacity {
-            complete(creep.try_id().unwrap());
+            if !self.transfer_to_link || !self.switch_to_link_transfer(creep, switch) {
+                complete(creep.try_id().unwrap());
+            }
             return;
         }
 
         if let Some(source) = self.target.resolve() {
-            if creep.pos().is_near_to(source.pos()) {
-                creep.harvest(&source).unwrap_or_else(|e| {
-                    debug!("couldn't harvest: {:?}", e);
-                    cancel(creep.try_id().unwrap());
-                });
-            } else {
-                let result = creep.move_to(&source);
+            // link harvesters wait at an empty source for it to regenerate, emptying into the
+            // link while they wait
+            if self.transfer_to_link && source.energy() == 0 && creep.pos().is_near_to(source.pos())
+            {
+                if crate::utils::energy(creep) > 0 {
+                    self.switch_to_link_transfer(creep, switch);
+                }
+                return;
+            }
 
-                if result.is_err() && result.err().unwrap() != ErrorCode::Tired {
+            match approach_then(creep, source.pos(), 1, || creep.harvest(&source)) {
+                Ok(_) => self.move_failure_count = 0,
+                Err(ApproachError::Action(e)) => {
+                    debug!("couldn't harvest: {:?}", e);
+                    cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+                }
+                Err(ApproachError::Move(_)) => {
                     self.move_failure_count += 1;
                     if self.move_failure_count >= 3 {
-                        cancel(creep.try_id().unwrap());
+                        cancel(creep.try_id().unwrap(), super::TaskOutcome::Unreachable);
                     }
-                } else {
-                    self.move_failure_count = 0;
                 }
-
-                // creep.move_to(&source).unwrap_or_else(|_e| {
-                //     // info!("couldn't move to harvest: {:?}", _e);
-
-                //     let mut sources = room.find(find::SOURCES_ACTIVE, None);
-                //     sources.sort_by_key(|a| 0 - a.energy());
-
-                //     for new_source in sources {
-                //         if source.try_id().unwrap() != new_source.try_id().unwrap() {
-                //             switch(
-                //                 creep.try_id().unwrap(),
-                //                 Box::new(HarvestSourceTask::new(new_source.try_id().unwrap())),
-                //             );
-                //             return;
-                //         }
-                //     }
-                // });
             }
         } else {
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
         }
     }
 
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Work]
+    }
+
     fn get_target_pos(&self) -> Option<screeps::Position> {
         self.target.resolve().map(|target| target.pos())
     }
@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 
 use log::*;
-use screeps::{Creep, HasPosition, MaybeHasTypedId, ObjectId, ResourceType, SharedCreepProperties};
+use screeps::{Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, SharedCreepProperties};
 
 pub struct HealTask {
     target: ObjectId<Creep>,
@@ -22,10 +22,10 @@ impl super::Task for HealTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
-        if creep.store().get_free_capacity(Some(ResourceType::Energy)) == 0 {
+        if crate::utils::free_energy(creep) == 0 {
             complete(creep.try_id().unwrap());
             return;
         }
@@ -35,7 +35,7 @@ impl super::Task for HealTask {
                 if creep.pos().is_near_to(target_creep.pos()) {
                     creep.heal(&target_creep).unwrap_or_else(|e| {
                         info!("couldn't heal: {:?}", e);
-                        cancel(creep.try_id().unwrap());
+                        cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
                     });
                 } else {
                     let _ = creep.move_to(&target_creep);
@@ -44,14 +44,22 @@ impl super::Task for HealTask {
                 complete(creep.try_id().unwrap());
             }
         } else {
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
         }
     }
 
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Heal]
+    }
+
     fn get_target_pos(&self) -> Option<screeps::Position> {
         self.target.resolve().map(|target| target.pos())
     }
 
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
     fn get_icon(&self) -> String {
         String::from("🚑")
     }
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use screeps::{Creep, MaybeHasTypedId, ObjectId};
+use screeps::{Creep, MaybeHasTypedId, ObjectId, Part};
 
 pub struct IdleTask {
     duration: u32,
@@ -20,7 +20,7 @@ impl super::Task for IdleTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        _cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         if self.duration == 0 {
@@ -30,6 +30,14 @@ impl super::Task for IdleTask {
         }
     }
 
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![]
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
     fn get_icon(&self) -> String {
         String::from("🕐")
     }
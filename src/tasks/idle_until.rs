@@ -1,12 +1,17 @@
 use std::fmt::Debug;
 
-use screeps::{Creep, MaybeHasTypedId, ObjectId};
+use screeps::{
+    game, Creep, HasStore, MaybeHasTypedId, ObjectId, Part, Resolvable, ResourceType, Source,
+};
 
 type UntilFn<T> = fn(&Creep, &T) -> bool;
 
 pub struct IdleUntilTask<T> {
     until: UntilFn<T>,
     pass_through: T,
+    timeout: Option<u32>,
+    timeout_task_list: Option<super::TaskList>,
+    started_at: Option<u32>,
 }
 
 impl<T> IdleUntilTask<T> {
@@ -14,9 +19,75 @@ impl<T> IdleUntilTask<T> {
         IdleUntilTask {
             until,
             pass_through,
+            timeout: None,
+            timeout_task_list: None,
+            started_at: None,
         }
     }
+
+    /// Gives up waiting after this many ticks, so the creep can be given something else to do
+    pub fn with_timeout(mut self, ticks: u32) -> IdleUntilTask<T> {
+        self.timeout = Some(ticks);
+        self
+    }
+
+    /// Switches the creep to the task list once the timeout is up, instead of giving up waiting
+    pub fn then_on_timeout(mut self, task_list: super::TaskList) -> IdleUntilTask<T> {
+        self.timeout_task_list = Some(task_list);
+        self
+    }
+}
+
+impl<T: HasStore + Resolvable> IdleUntilTask<ObjectId<T>> {
+    /// Waits until the store has energy in it. Stops waiting if the store is destroyed.
+    pub fn has_energy(store: ObjectId<T>) -> IdleUntilTask<ObjectId<T>> {
+        IdleUntilTask::new(
+            |_, store| {
+                store
+                    .resolve()
+                    .map(|store| store.store().get_used_capacity(Some(ResourceType::Energy)) > 0)
+                    .unwrap_or(true)
+            },
+            store,
+        )
+    }
+
+    /// Same as `has_energy`, but also stops waiting once the creep has energy of its own to use
+    pub fn creep_or_store_has_energy(store: ObjectId<T>) -> IdleUntilTask<ObjectId<T>> {
+        IdleUntilTask::new(
+            |creep, store| {
+                crate::utils::energy(creep) > 0
+                    || store
+                        .resolve()
+                        .map(|store| {
+                            store.store().get_used_capacity(Some(ResourceType::Energy)) > 0
+                        })
+                        .unwrap_or(true)
+            },
+            store,
+        )
+    }
 }
+
+impl IdleUntilTask<ObjectId<Source>> {
+    /// Waits until the source has energy and a free tile next to it. Stops waiting if the source
+    /// can't be seen.
+    pub fn source_has_free_slot(source: ObjectId<Source>) -> IdleUntilTask<ObjectId<Source>> {
+        IdleUntilTask::new(
+            |creep, source| {
+                let source = match source.resolve() {
+                    Some(source) => source,
+                    None => return true,
+                };
+                let source_info = crate::metadata::SourceInfo::new(&source, Some(creep));
+                source.energy() > 0
+                    && source_info.nearby_creep_count < source_info.non_wall_terrain_count
+            },
+            source,
+        )
+    }
+}
+
 impl<T> super::Task for IdleUntilTask<T> {
     fn get_type(&self) -> super::TaskType {
         super::TaskType::IdleUntil
@@ -26,14 +97,33 @@ impl<T> super::Task for IdleUntilTask<T> {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         if (self.until)(creep, &self.pass_through) {
             complete(creep.try_id().unwrap());
+            return;
+        }
+
+        let started_at = *self.started_at.get_or_insert(game::time());
+        if let Some(timeout) = self.timeout {
+            if game::time() - started_at >= timeout {
+                match self.timeout_task_list.take() {
+                    Some(task_list) => switch(creep.try_id().unwrap(), task_list),
+                    None => cancel(creep.try_id().unwrap(), super::TaskOutcome::TimedOut),
+                }
+            }
         }
     }
 
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![]
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
     fn get_icon(&self) -> String {
         String::from("🕐")
     }
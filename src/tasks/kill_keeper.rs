@@ -0,0 +1,138 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    find, game, Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, Position,
+    SharedCreepProperties, StructureObject, StructureType,
+};
+
+use crate::config;
+use crate::metadata::SOURCE_KEEPER_USERNAME;
+use crate::utils;
+
+/// Guards a keeper lair, killing its source keeper whenever one spawns and waiting next to the
+/// lair in between. The lair is tracked by position since the room usually isn't visible when the
+/// task is created.
+pub struct KillKeeperTask {
+    lair_pos: Position,
+}
+
+impl KillKeeperTask {
+    pub fn new(lair_pos: Position) -> KillKeeperTask {
+        KillKeeperTask { lair_pos }
+    }
+}
+
+impl super::Task for KillKeeperTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::KillKeeper
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        _complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        // the lair is no longer guarded once its flag is removed
+        let is_flagged = game::flags()
+            .values()
+            .any(|f| f.name().starts_with("keepers", 0) && f.pos() == self.lair_pos);
+        if !is_flagged {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+
+        let room = creep.room();
+        if room.is_none() {
+            return;
+        }
+        let room = room.unwrap();
+        if room.name() != self.lair_pos.room_name() {
+            let _ = creep.move_to(self.lair_pos);
+            return;
+        }
+
+        let lair = match utils::nearest_structure(self.lair_pos, &room, StructureType::KeeperLair) {
+            Some(StructureObject::StructureKeeperLair(lair)) if lair.pos() == self.lair_pos => {
+                Some(lair)
+            }
+            _ => None,
+        };
+        if lair.is_none() {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+        let lair = lair.unwrap();
+
+        let keeper = room
+            .find(find::HOSTILE_CREEPS, None)
+            .into_iter()
+            .filter(|c| {
+                c.owner().username() == SOURCE_KEEPER_USERNAME
+                    && c.pos()
+                        .in_range_to(self.lair_pos, config::KEEPER_DANGER_RANGE)
+            })
+            .min_by_key(|c| creep.pos().get_range_to(c.pos()));
+
+        if let Some(keeper) = keeper {
+            if creep.pos().is_near_to(keeper.pos()) {
+                creep.attack(&keeper).unwrap_or_else(|e| {
+                    info!("couldn't attack source keeper: {:?}", e);
+                });
+            } else {
+                // attacking and healing can't happen on the same tick, so heal on the way in
+                if creep.hits() < creep.hits_max() {
+                    let _ = creep.heal(creep);
+                }
+                let _ = creep.move_to(&keeper);
+            }
+            return;
+        }
+
+        if creep.hits() < creep.hits_max() {
+            let _ = creep.heal(creep);
+        }
+
+        // wait next to the lair once the next keeper is about to spawn
+        let ticks_to_spawn = lair.ticks_to_spawn().unwrap_or(0);
+
+        let wait_range = if ticks_to_spawn <= config::KEEPER_SPAWN_WARNING_TICKS {
+            1
+        } else {
+            3
+        };
+        if !creep.pos().in_range_to(self.lair_pos, wait_range) {
+            let _ = creep.move_to(self.lair_pos);
+        }
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Attack, Part::Heal]
+    }
+
+    fn get_target_pos(&self) -> Option<Position> {
+        Some(self.lair_pos)
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("🗡️")
+    }
+}
+
+impl Debug for KillKeeperTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Kill keepers at lair ({}, {}) in room {}",
+            self.lair_pos.x().u8(),
+            self.lair_pos.y().u8(),
+            self.lair_pos.room_name()
+        )
+    }
+}
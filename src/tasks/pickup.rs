@@ -0,0 +1,89 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, Resource, SharedCreepProperties,
+};
+
+pub struct PickupTask {
+    target: ObjectId<Resource>,
+}
+
+impl PickupTask {
+    pub fn new(target: ObjectId<Resource>) -> PickupTask {
+        PickupTask { target }
+    }
+}
+
+impl super::Task for PickupTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::Pickup
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        if crate::utils::is_full(creep) {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        // the resource no longer resolving means it was picked up or decayed
+        let resource = self.target.resolve();
+        if resource.is_none() {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        let resource = resource.unwrap();
+        if creep.pos().is_near_to(resource.pos()) {
+            match creep.pickup(&resource) {
+                Ok(()) => complete(creep.try_id().unwrap()),
+                Err(e) => {
+                    info!("couldn't pick up resource: {:?}", e);
+                    cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+                }
+            }
+        } else {
+            let _ = crate::pathing::travel_to(creep, resource.pos());
+        }
+    }
+
+    fn get_target_pos(&self) -> Option<screeps::Position> {
+        self.target.resolve().map(|target| target.pos())
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Carry]
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("🫳")
+    }
+}
+
+impl Debug for PickupTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(resource) = self.target.resolve() {
+            write!(
+                f,
+                "Pick up {} {:?} at ({}, {}) in room {}",
+                resource.amount(),
+                resource.resource_type(),
+                resource.pos().x().u8(),
+                resource.pos().y().u8(),
+                resource.pos().room_name()
+            )
+        } else {
+            write!(f, "Pick up ({:?})", self.target)
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, SharedCreepProperties, StructureSpawn,
+};
+
+pub struct RecycleTask {
+    spawn: ObjectId<StructureSpawn>,
+}
+
+impl RecycleTask {
+    pub fn new(spawn: ObjectId<StructureSpawn>) -> RecycleTask {
+        RecycleTask { spawn }
+    }
+}
+
+impl super::Task for RecycleTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::Recycle
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        _complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        let spawn = self.spawn.resolve();
+        if spawn.is_none() {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+        let spawn = spawn.unwrap();
+
+        if creep.pos().is_near_to(spawn.pos()) {
+            spawn.recycle_creep(creep).unwrap_or_else(|e| {
+                info!("couldn't recycle {}: {:?}", creep.name(), e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            });
+        } else {
+            let _ = creep.move_to(&spawn);
+        }
+    }
+
+    fn get_target_pos(&self) -> Option<screeps::Position> {
+        self.spawn.resolve().map(|spawn| spawn.pos())
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![]
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("♻️")
+    }
+}
+
+impl Debug for RecycleTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(spawn) = self.spawn.resolve() {
+            write!(
+                f,
+                "Recycle at ({}, {}) in room {}",
+                spawn.pos().x().u8(),
+                spawn.pos().y().u8(),
+                spawn.pos().room_name()
+            )
+        } else {
+            write!(f, "Recycle ({:?})", self.spawn)
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt::Debug;
+
+use log::*;
+use screeps::{
+    Creep, HasPosition, MaybeHasTypedId, ObjectId, Part, SharedCreepProperties, StructureSpawn,
+};
+
+use crate::pathing::{approach_then, Approach, ApproachError};
+
+/// Renews an idle creep at a spawn. Completes after each renewal so the creep is offered work
+/// again every tick, and is only handed out again while it's still idle.
+pub struct RenewTask {
+    spawn: ObjectId<StructureSpawn>,
+}
+
+impl RenewTask {
+    pub fn new(spawn: ObjectId<StructureSpawn>) -> RenewTask {
+        RenewTask { spawn }
+    }
+}
+
+impl super::Task for RenewTask {
+    fn get_type(&self) -> super::TaskType {
+        super::TaskType::Renew
+    }
+
+    fn execute(
+        &mut self,
+        creep: &Creep,
+        complete: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+    ) {
+        let spawn = self.spawn.resolve();
+        if spawn.is_none() {
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
+            return;
+        }
+        let spawn = spawn.unwrap();
+
+        // spawning takes priority over renewing
+        if spawn.spawning().is_some() {
+            complete(creep.try_id().unwrap());
+            return;
+        }
+
+        match approach_then(creep, spawn.pos(), 1, || spawn.renew_creep(creep)) {
+            Ok(Approach::Acted) => complete(creep.try_id().unwrap()),
+            Ok(Approach::Moved) => {}
+            Err(ApproachError::Action(e)) => {
+                debug!("couldn't renew {}: {:?}", creep.name(), e);
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+            }
+            Err(ApproachError::Move(_)) => {
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Unreachable);
+            }
+        }
+    }
+
+    fn get_target_pos(&self) -> Option<screeps::Position> {
+        self.spawn.resolve().map(|spawn| spawn.pos())
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![]
+    }
+
+    fn requires_energy(&self) -> bool {
+        false
+    }
+
+    fn get_icon(&self) -> String {
+        String::from("🔋")
+    }
+}
+
+impl Debug for RenewTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(spawn) = self.spawn.resolve() {
+            write!(
+                f,
+                "Renew at ({}, {}) in room {}",
+                spawn.pos().x().u8(),
+                spawn.pos().y().u8(),
+                spawn.pos().room_name()
+            )
+        } else {
+            write!(f, "Renew ({:?})", self.spawn)
+        }
+    }
+}
@@ -1,17 +1,53 @@
 use std::fmt::Debug;
 
 use log::*;
-use screeps::{
-    Creep, HasPosition, MaybeHasTypedId, ObjectId, ResourceType, SharedCreepProperties, Structure,
-};
+use screeps::{Creep, HasPosition, MaybeHasTypedId, ObjectId, SharedCreepProperties, Structure};
 
+use crate::pathing::{approach_then, Approach, ApproachError};
+
+#[derive(Clone)]
 pub struct RepairTask {
     target: ObjectId<Structure>,
+    hits_ratio: f32,
+    max_hits: Option<u32>,
+    urgent: bool,
 }
 
 impl RepairTask {
     pub fn new(target: ObjectId<Structure>) -> RepairTask {
-        RepairTask { target }
+        RepairTask {
+            target,
+            hits_ratio: 1.0,
+            max_hits: None,
+            urgent: false,
+        }
+    }
+
+    /// Stops repairing once the structure reaches this fraction of its max hits
+    pub fn with_hits_ratio(mut self, hits_ratio: f32) -> RepairTask {
+        self.hits_ratio = hits_ratio;
+        self
+    }
+
+    /// Stops repairing once the structure reaches this many hits. Walls and ramparts have far more
+    /// max hits than is worth repairing to.
+    pub fn with_max_hits(mut self, max_hits: u32) -> RepairTask {
+        self.max_hits = Some(max_hits);
+        self
+    }
+
+    /// Puts the repair ahead of every other repair, for structures about to be destroyed by decay
+    pub fn with_urgency(mut self) -> RepairTask {
+        self.urgent = true;
+        self
+    }
+
+    fn get_target_hits(&self, structure: &Structure) -> u32 {
+        let target_hits = (structure.hits_max() as f32 * self.hits_ratio) as u32;
+        match self.max_hits {
+            Some(max_hits) => target_hits.min(max_hits),
+            None => target_hits,
+        }
     }
 }
 
@@ -24,26 +60,30 @@ impl super::Task for RepairTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
-        if creep.store().get_used_capacity(Some(ResourceType::Energy)) == 0 {
-            complete(creep.try_id().unwrap());
+        if crate::utils::is_empty(creep) {
+            if !super::refuel_then(creep, Box::new(self.clone()), switch) {
+                complete(creep.try_id().unwrap());
+            }
             return;
         }
 
         if let Some(structure) = self.target.resolve() {
-            if creep.pos().is_near_to(structure.pos()) {
-                creep.repair(&structure).unwrap_or_else(|e| {
-                    info!("couldn't repair: {:?}", e);
-                });
-                if structure.hits() >= structure.hits_max()
-                    || creep.store().get_used_capacity(Some(ResourceType::Energy)) == 0
-                {
-                    cancel(creep.try_id().unwrap());
-                }
-            } else {
-                let _ = creep.move_to(&structure);
+            if structure.hits() >= self.get_target_hits(&structure) {
+                complete(creep.try_id().unwrap());
+                return;
+            }
+
+            match approach_then(creep, structure.pos(), 3, || creep.repair(&structure)) {
+                Ok(Approach::Moved) | Err(ApproachError::Move(_)) => return,
+                Ok(Approach::Acted) => {}
+                Err(ApproachError::Action(e)) => info!("couldn't repair: {:?}", e),
+            }
+
+            if crate::utils::is_empty(creep) {
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::OutOfEnergy);
             }
         } else {
             complete(creep.try_id().unwrap());
@@ -55,6 +95,10 @@ impl super::Task for RepairTask {
     }
 
     fn get_priority(&self) -> u32 {
+        if self.urgent {
+            return 0;
+        }
+
         self.target
             .resolve()
             .map(|target| target.hits())
@@ -1,11 +1,11 @@
 use std::fmt::Debug;
 
-use screeps::{Creep, ObjectId, Part};
+use screeps::{Creep, ErrorCode, ObjectId, Part};
 
 use super::TaskList;
 
 type CompleteCallback = Box<dyn FnOnce(ObjectId<Creep>)>;
-type CancelCallback = Box<dyn FnOnce(ObjectId<Creep>)>;
+type CancelCallback = Box<dyn FnOnce(ObjectId<Creep>, TaskOutcome)>;
 type SwitchCallback = Box<dyn FnOnce(ObjectId<Creep>, TaskList)>;
 
 pub trait Task: Debug {
@@ -48,12 +48,21 @@ pub trait Task: Debug {
 #[derive(Debug, PartialEq)]
 pub enum TaskType {
     Attack,
+    AttackStructure,
+    Boost,
     Build,
     Claim,
+    DefendRampart,
+    Drop,
+    HarvestMineral,
     HarvestSource,
     Heal,
     Idle,
     IdleUntil,
+    KillKeeper,
+    Pickup,
+    Recycle,
+    Renew,
     Repair,
     Transfer,
     Travel,
@@ -61,3 +70,25 @@ pub enum TaskType {
     Upgrade,
     Withdraw,
 }
+
+/// How a task ended, logged when the creep moves on to its next task
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskOutcome {
+    Completed,
+    /// The target was destroyed, used up or can no longer be seen
+    TargetGone,
+    /// The target no longer has what the task needs from it
+    TargetEmpty,
+    /// The creep ran out of energy before the task was done
+    OutOfEnergy,
+    /// The creep couldn't find a way to the target
+    Unreachable,
+    /// The target ran out of the room or out of chase range
+    TargetFled,
+    /// The creep waited too long for something that never happened
+    TimedOut,
+    /// The creep is about to die
+    Dying,
+    /// The game rejected the action
+    Failed(ErrorCode),
+}
@@ -52,6 +52,10 @@ impl TaskList {
         return Some(self.tasks.get(self.current_task_idx)?.as_ref());
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Task> {
+        self.tasks.iter().map(|task| task.as_ref())
+    }
+
     pub fn get_primary_task(&self) -> Option<&dyn Task> {
         return Some(self.tasks.get(self.primary_task_idx)?.as_ref());
     }
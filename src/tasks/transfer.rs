@@ -2,51 +2,59 @@ use std::fmt::Debug;
 
 use log::*;
 use screeps::{
-    find, Creep, HasPosition, HasStore, MaybeHasTypedId, ObjectId, Resolvable, ResourceType,
-    SharedCreepProperties, StructureExtension, StructureObject, StructureProperties, Transferable,
+    Creep, HasPosition, HasStore, MaybeHasTypedId, ObjectId, OwnedStructureProperties, Part,
+    Position, Resolvable, ResourceType, SharedCreepProperties, StructureExtension, StructureObject,
+    StructureType, Transferable,
 };
 
+use crate::pathing::{approach_along_route_then, approach_then, ApproachError};
+
 pub struct TransferTask<T: Transferable + Resolvable + HasStore> {
     target: ObjectId<T>,
+    resource: ResourceType,
+    route_from: Option<Position>,
 }
 
 impl<T: Transferable + Resolvable + HasStore> TransferTask<T> {
     pub fn new(target: ObjectId<T>) -> TransferTask<T> {
-        TransferTask { target }
+        TransferTask::with_resource(target, ResourceType::Energy)
+    }
+
+    pub fn with_resource(target: ObjectId<T>, resource: ResourceType) -> TransferTask<T> {
+        TransferTask {
+            target,
+            resource,
+            route_from: None,
+        }
+    }
+
+    /// Travel to the target along the cached route from `from`. Used by creeps that repeatedly
+    /// move between the same two positions.
+    pub fn via_route(mut self, from: Position) -> TransferTask<T> {
+        self.route_from = Some(from);
+        self
     }
 
     fn get_nearest_extension(&self, creep: &Creep) -> Option<ObjectId<StructureExtension>> {
-        // Get extensions that require energy and sort by distance
-        let structures = creep.room().unwrap().find(find::MY_STRUCTURES, None);
-        let mut extensions = structures
-            .iter()
-            .filter(|s| {
-                if s.structure_type() == screeps::StructureType::Extension {
-                    if let StructureObject::StructureExtension(extension) = s {
-                        if extension
+        let room = creep.room()?;
+        let extension = crate::utils::nearest_structure_with(
+            creep.pos(),
+            &room,
+            StructureType::Extension,
+            |s| {
+                if let StructureObject::StructureExtension(extension) = s {
+                    return extension.my()
+                        && extension
                             .store()
                             .get_free_capacity(Some(ResourceType::Energy))
-                            > 0
-                        {
-                            return true;
-                        }
-                    }
+                            > 0;
                 }
                 false
-            })
-            .map(|s| {
-                let pos = s.pos();
-                (s, creep.pos().get_range_to(pos))
-            })
-            .collect::<Vec<_>>();
-
-        extensions.sort_by(|(_, a), (_, b)| a.cmp(b));
-
-        let extension = extensions.first();
-        if let Some(extension_data) = extension {
-            if let StructureObject::StructureExtension(extension) = extension_data.0 {
-                return Some(extension.try_id().unwrap());
-            }
+            },
+        );
+
+        if let Some(StructureObject::StructureExtension(extension)) = extension {
+            return extension.try_id();
         }
         None
     }
@@ -61,24 +69,24 @@ impl<T: Transferable + Resolvable + HasStore> super::Task for TransferTask<T> {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
-        if creep.store().get_used_capacity(Some(ResourceType::Energy)) == 0 {
+        if creep.store().get_used_capacity(Some(self.resource)) == 0 {
             complete(creep.try_id().unwrap());
             return;
         }
 
         let target = self.target.resolve();
         if target.is_none() {
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
             return;
         }
 
         let target = target.unwrap();
         let creep_type = super::utils::get_creep_type(creep);
         if creep_type != "source_harvester"
-            && target.store().get_free_capacity(Some(ResourceType::Energy)) == 0
+            && target.store().get_free_capacity(Some(self.resource)) == 0
         {
             // if let Some(extension_id) = self.get_nearest_extension(creep) {
             //     switch(
@@ -92,33 +100,36 @@ impl<T: Transferable + Resolvable + HasStore> super::Task for TransferTask<T> {
             return;
         }
 
-        if creep.pos().is_near_to(target.pos()) {
-            creep
-                .transfer(&target, ResourceType::Energy, None)
-                .unwrap_or_else(|e| {
-                    info!("couldn't transfer: {:?}", e);
-
-                    // if creep_type != "source_harvester" {
-                    //     if let Some(extension_id) = self.get_nearest_extension(creep) {
-                    //         switch(
-                    //             creep.try_id().unwrap(),
-                    //             super::TaskList::new(
-                    //                 vec![Box::new(TransferTask::new(extension_id))],
-                    //                 false,
-                    //                 0,
-                    //             ),
-                    //         );
-                    //         return;
-                    //     }
-                    // }
-
-                    cancel(creep.try_id().unwrap());
-                });
-        } else {
-            let _ = creep.move_to(&target);
+        let transfer = || creep.transfer(&target, self.resource, None);
+        let result = match self.route_from {
+            Some(from) => approach_along_route_then(creep, from, target.pos(), 1, transfer),
+            None => approach_then(creep, target.pos(), 1, transfer),
+        };
+        if let Err(ApproachError::Action(e)) = result {
+            info!("couldn't transfer: {:?}", e);
+
+            // if creep_type != "source_harvester" {
+            //     if let Some(extension_id) = self.get_nearest_extension(creep) {
+            //         switch(
+            //             creep.try_id().unwrap(),
+            //             super::TaskList::new(
+            //                 vec![Box::new(TransferTask::new(extension_id))],
+            //                 false,
+            //                 0,
+            //             ),
+            //         );
+            //         return;
+            //     }
+            // }
+
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
         }
     }
 
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Carry]
+    }
+
     fn get_target_pos(&self) -> Option<screeps::Position> {
         self.target.resolve().map(|target| target.pos())
     }
@@ -128,7 +139,7 @@ impl<T: Transferable + Resolvable + HasStore> super::Task for TransferTask<T> {
     }
 
     fn requires_energy(&self) -> bool {
-        true
+        self.resource == ResourceType::Energy
     }
 }
 
@@ -137,7 +148,8 @@ impl<T: Transferable + Resolvable + HasStore> Debug for TransferTask<T> {
         if let Some(structure) = self.target.resolve() {
             write!(
                 f,
-                "Transfer energy to ({}, {}) in room {}",
+                "Transfer {:?} to ({}, {}) in room {}",
+                self.resource,
                 structure.pos().x().u8(),
                 structure.pos().y().u8(),
                 structure.pos().room_name(),
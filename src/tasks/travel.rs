@@ -24,12 +24,12 @@ impl<T: HasPosition + Resolvable> super::Task for TravelTask<T> {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         let target = self.target.resolve();
         if target.is_none() {
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
             return;
         }
 
@@ -39,13 +39,13 @@ impl<T: HasPosition + Resolvable> super::Task for TravelTask<T> {
             return;
         }
 
-        creep.move_to(target).unwrap_or_else(|e| match e {
+        crate::pathing::travel_to(creep, target.pos()).unwrap_or_else(|e| match e {
             screeps::ErrorCode::Tired => {
                 // ignore
             }
             _ => {
                 info!("cant move to location: {:?}", e);
-                cancel(creep.try_id().unwrap());
+                cancel(creep.try_id().unwrap(), super::TaskOutcome::Unreachable);
             }
         });
     }
@@ -56,7 +56,7 @@ impl super::Task for TravelDumbTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         if creep.pos().is_near_to(self.target) {
@@ -71,7 +71,7 @@ impl super::Task for TravelDumbTask {
                 }
                 _ => {
                     info!("cant move to location: {:?}", e);
-                    cancel(creep.try_id().unwrap());
+                    cancel(creep.try_id().unwrap(), super::TaskOutcome::Unreachable);
                 }
             });
     }
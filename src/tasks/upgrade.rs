@@ -2,10 +2,11 @@ use std::fmt::Debug;
 
 use log::*;
 use screeps::{
-    Creep, ErrorCode, HasPosition, MaybeHasTypedId, ObjectId, ResourceType, SharedCreepProperties,
+    Creep, ErrorCode, HasPosition, MaybeHasTypedId, ObjectId, SharedCreepProperties,
     StructureController,
 };
 
+#[derive(Clone)]
 pub struct UpgradeTask {
     target: ObjectId<StructureController>,
 }
@@ -25,11 +26,13 @@ impl super::Task for UpgradeTask {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
-        _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
+        switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
-        if creep.store().get_used_capacity(Some(ResourceType::Energy)) == 0 {
-            complete(creep.try_id().unwrap());
+        if crate::utils::is_empty(creep) {
+            if !super::refuel_then(creep, Box::new(self.clone()), switch) {
+                complete(creep.try_id().unwrap());
+            }
             return;
         }
 
@@ -42,11 +45,11 @@ impl super::Task for UpgradeTask {
                     }
                     _ => {
                         info!("couldn't upgrade: {:?}", e);
-                        cancel(creep.try_id().unwrap());
+                        cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
                     }
                 });
         } else {
-            cancel(creep.try_id().unwrap());
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
         }
     }
 
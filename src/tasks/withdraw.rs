@@ -2,17 +2,45 @@ use std::fmt::Debug;
 
 use log::*;
 use screeps::{
-    Creep, HasPosition, HasStore, MaybeHasTypedId, ObjectId, Resolvable, ResourceType,
-    SharedCreepProperties, Withdrawable,
+    Creep, HasPosition, HasStore, MaybeHasTypedId, MoveToOptions, ObjectId, Part, Position,
+    Resolvable, ResourceType, SharedCreepProperties, Withdrawable,
 };
 
+use crate::pathing::{approach_along_route_then, approach_then, is_surrounded, ApproachError};
+
 pub struct WithdrawTask<T: Withdrawable + Resolvable + HasStore> {
     target: ObjectId<T>,
+    resource: ResourceType,
+    route_from: Option<Position>,
+    queue_when_crowded: bool,
 }
 
 impl<T: Withdrawable + Resolvable + HasStore> WithdrawTask<T> {
     pub fn new(target: ObjectId<T>) -> WithdrawTask<T> {
-        WithdrawTask { target }
+        WithdrawTask::with_resource(target, ResourceType::Energy)
+    }
+
+    pub fn with_resource(target: ObjectId<T>, resource: ResourceType) -> WithdrawTask<T> {
+        WithdrawTask {
+            target,
+            resource,
+            route_from: None,
+            queue_when_crowded: false,
+        }
+    }
+
+    /// Travel to the target along the cached route from `from`. Used by creeps that repeatedly
+    /// move between the same two positions.
+    pub fn via_route(mut self, from: Position) -> WithdrawTask<T> {
+        self.route_from = Some(from);
+        self
+    }
+
+    /// Waits two tiles from the target while every side of it is taken, and otherwise heads for
+    /// whichever side is free. Used for links, which several creeps withdraw from at once.
+    pub fn queue_when_crowded(mut self) -> WithdrawTask<T> {
+        self.queue_when_crowded = true;
+        self
     }
 }
 
@@ -25,36 +53,58 @@ impl<T: Withdrawable + Resolvable + HasStore> super::Task for WithdrawTask<T> {
         &mut self,
         creep: &Creep,
         complete: Box<dyn FnOnce(ObjectId<Creep>)>,
-        cancel: Box<dyn FnOnce(ObjectId<Creep>)>,
+        cancel: Box<dyn FnOnce(ObjectId<Creep>, super::TaskOutcome)>,
         _switch: Box<dyn FnOnce(ObjectId<Creep>, super::TaskList)>,
     ) {
         let target = self.target.resolve();
         if target.is_none() {
-            error!("there is no target to withdraw energy from");
-            cancel(creep.try_id().unwrap());
+            error!("there is no target to withdraw {:?} from", self.resource);
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::TargetGone);
             return;
         }
         let target = target.unwrap();
 
-        // If the creep has energy, or the target has no energy, complete the task
-        if creep.store().get_used_capacity(Some(ResourceType::Energy))
-            == creep.store().get_capacity(Some(ResourceType::Energy))
-            || target.store().get_used_capacity(Some(ResourceType::Energy)) == 0
+        // If the creep is full, or the target has none of the resource, complete the task
+        if creep.store().get_used_capacity(Some(self.resource))
+            == creep.store().get_capacity(Some(self.resource))
+            || target.store().get_used_capacity(Some(self.resource)) == 0
         {
             complete(creep.try_id().unwrap());
             return;
         }
 
-        if creep.pos().is_near_to(target.pos()) {
-            creep
-                .withdraw(&target, ResourceType::Energy, None)
-                .unwrap_or_else(|e| {
-                    debug!("couldn't withdraw: {:?}", e);
-                    cancel(creep.try_id().unwrap());
-                });
-        } else {
-            let _ = creep.move_to(&target);
+        if self.queue_when_crowded && !creep.pos().is_near_to(target.pos()) {
+            if is_surrounded(target.pos()) {
+                if !creep.pos().in_range_to(target.pos(), 2) {
+                    let _ = creep
+                        .move_to_with_options(target.pos(), Some(MoveToOptions::new().range(2)));
+                }
+                return;
+            }
+
+            // close by, the path has to go around the creeps already at the target
+            if creep.pos().in_range_to(target.pos(), 2) {
+                let _ = creep.move_to_with_options(
+                    target.pos(),
+                    Some(MoveToOptions::new().ignore_creeps(false)),
+                );
+                return;
+            }
         }
+
+        let withdraw = || creep.withdraw(&target, self.resource, None);
+        let result = match self.route_from {
+            Some(from) => approach_along_route_then(creep, from, target.pos(), 1, withdraw),
+            None => approach_then(creep, target.pos(), 1, withdraw),
+        };
+        if let Err(ApproachError::Action(e)) = result {
+            debug!("couldn't withdraw: {:?}", e);
+            cancel(creep.try_id().unwrap(), super::TaskOutcome::Failed(e));
+        }
+    }
+
+    fn requires_body_parts(&self) -> Vec<Part> {
+        vec![Part::Carry]
     }
 
     fn get_target_pos(&self) -> Option<screeps::Position> {
@@ -75,7 +125,8 @@ impl<T: Withdrawable + Resolvable + HasStore> Debug for WithdrawTask<T> {
         if let Some(target) = self.target.resolve() {
             write!(
                 f,
-                "Withdraw energy at ({}, {}) in room {} [{}/{}]",
+                "Withdraw {:?} at ({}, {}) in room {} [{}/{}]",
+                self.resource,
                 target.pos().x().u8(),
                 target.pos().y().u8(),
                 target.pos().room_name(),
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+
+use log::*;
+use screeps::{game, HasStore, ResourceType, RoomName, StructureObject, StructureTerminal};
+
+use crate::config;
+use crate::metadata::{room_resource_totals, Feature, RoomInfo};
+
+/// Returns the energy a terminal spends to send `amount` of a resource between two rooms
+pub fn get_send_cost(amount: u32, from: RoomName, to: RoomName) -> u32 {
+    let distance = game::map::get_room_linear_distance(from, to, true) as f64;
+    (amount as f64 * (1.0 - (-distance / 30.0).exp())).ceil() as u32
+}
+
+/// Queues `amount` of a resource to be sent to the room. Requests for the same room and resource
+/// are combined and sent together once they reach `MIN_TERMINAL_SEND_AMOUNT`, since many small
+/// sends waste more energy than one large one.
+pub fn request_transfer(destination: RoomName, resource: ResourceType, amount: u32) {
+    super::TERMINAL_TRANSFERS.with(|transfers| {
+        *transfers
+            .borrow_mut()
+            .entry((destination, resource))
+            .or_insert(0) += amount;
+    });
+}
+
+/// Sends every queued transfer that's large enough, from whichever room can send it for the least
+/// energy. Each terminal sends at most once per tick.
+pub fn process_transfers(room_info_map: &HashMap<RoomName, RoomInfo>) {
+    let pending = super::TERMINAL_TRANSFERS.with(|transfers| transfers.borrow().clone());
+    let mut used_terminals = HashSet::new();
+
+    for ((destination, resource), amount) in pending {
+        if amount < config::MIN_TERMINAL_SEND_AMOUNT {
+            continue;
+        }
+
+        let cheapest = room_info_map
+            .values()
+            .filter(|room_info| {
+                room_info.room.name() != destination
+                    && !used_terminals.contains(&room_info.room.name())
+                    && room_info.is_feature_enabled(Feature::Terminal)
+            })
+            .filter_map(|room_info| {
+                let terminal = get_terminal(room_info)?;
+                if terminal.cooldown() > 0 {
+                    return None;
+                }
+
+                let cost = get_send_cost(amount, room_info.room.name(), destination);
+                let store = terminal.store();
+                let energy_needed = if resource == ResourceType::Energy {
+                    amount + cost
+                } else {
+                    // couriers carry it over from storage first, see `get_terminal_shortfall`
+                    if store.get_used_capacity(Some(resource)) < amount {
+                        return None;
+                    }
+                    cost
+                };
+                if store.get_used_capacity(Some(ResourceType::Energy)) < energy_needed {
+                    return None;
+                }
+
+                Some((cost, terminal))
+            })
+            .min_by_key(|(cost, _)| *cost);
+
+        let (cost, terminal) = match cheapest {
+            Some(cheapest) => cheapest,
+            None => {
+                debug!(
+                    "no terminal can send {} {:?} to {} yet",
+                    amount, resource, destination
+                );
+                continue;
+            }
+        };
+
+        let from = terminal.room().unwrap().name();
+        match terminal.send(resource, amount, destination, None) {
+            Ok(()) => {
+                info!(
+                    "sent {} {:?} from {} to {} for {} energy",
+                    amount, resource, from, destination, cost
+                );
+                used_terminals.insert(from);
+                super::TERMINAL_TRANSFERS.with(|transfers| {
+                    transfers.borrow_mut().remove(&(destination, resource));
+                });
+            }
+            Err(e) => info!("terminal in {} couldn't send: {:?}", from, e),
+        }
+    }
+}
+
+/// Returns a resource the room has enough of in stock to send a queued transfer, but not in its
+/// terminal, so couriers know to carry it over from storage
+pub fn get_terminal_shortfall(room_info: &RoomInfo) -> Option<ResourceType> {
+    let terminal = get_terminal(room_info)?;
+    let totals = room_resource_totals(room_info);
+    let pending = super::TERMINAL_TRANSFERS.with(|transfers| transfers.borrow().clone());
+
+    pending
+        .into_iter()
+        .find(|((destination, resource), amount)| {
+            let in_stock = totals.get(resource).copied().unwrap_or(0);
+            *destination != room_info.room.name()
+                && *resource != ResourceType::Energy
+                && *amount >= config::MIN_TERMINAL_SEND_AMOUNT
+                && in_stock >= *amount
+                && terminal.store().get_used_capacity(Some(*resource)) < *amount
+        })
+        .map(|((_, resource), _)| resource)
+}
+
+fn get_terminal(room_info: &RoomInfo) -> Option<StructureTerminal> {
+    room_info.my_structures.iter().find_map(|s| {
+        if let StructureObject::StructureTerminal(terminal) = s {
+            return Some(terminal.clone());
+        }
+        None
+    })
+}
@@ -1,6 +1,14 @@
 use core::panic;
+use std::collections::HashMap;
 
-use screeps::{Creep, OwnedStructureProperties, Room, RoomName, SharedCreepProperties};
+use screeps::{
+    find, game, Creep, HasPosition, HasTypedId, MaybeHasTypedId, ObjectId,
+    OwnedStructureProperties, Position, ResourceType, Room, RoomName, SharedCreepProperties,
+    Source, StructureLink, StructureObject, StructureProperties, StructureType,
+};
+use wasm_bindgen::JsValue;
+
+use crate::config;
 
 use log::*;
 
@@ -12,6 +20,167 @@ pub fn get_creep_type(creep: &Creep) -> String {
         .collect::<String>()
 }
 
+/// Returns how much energy the creep is carrying
+pub fn energy(creep: &Creep) -> u32 {
+    creep.store().get_used_capacity(Some(ResourceType::Energy))
+}
+
+/// Returns how much more energy the creep can carry. This can be negative if the creep lost carry
+/// parts while full.
+pub fn free_energy(creep: &Creep) -> i32 {
+    creep.store().get_free_capacity(Some(ResourceType::Energy))
+}
+
+/// Returns true if the creep isn't carrying any energy
+pub fn is_empty(creep: &Creep) -> bool {
+    energy(creep) == 0
+}
+
+/// Returns true if the creep can't carry anything more
+pub fn is_full(creep: &Creep) -> bool {
+    creep.store().get_free_capacity(None) == 0
+}
+
+/// Returns the room the creep was spawned for. Creep names are formatted as
+/// `{role}-{home_room}-v{version}-{time}-{n}`; creeps spawned before the home room was
+/// encoded return None.
+pub fn get_creep_home_room(creep: &Creep) -> Option<RoomName> {
+    let name = creep.name();
+    let home_room = name.split('-').nth(1)?;
+    RoomName::new(home_room).ok()
+}
+
+/// Returns every (link, source) pair in the room where the link is close enough to the source for
+/// a harvester to use both. Every harvester looks these up, so they're cached per room for the
+/// tick.
+pub fn get_source_links(room: &Room) -> Vec<(ObjectId<StructureLink>, ObjectId<Source>)> {
+    let room_name = room.name();
+    let time = game::time();
+
+    let cached = super::SOURCE_LINK_CACHE.with(|cache| {
+        cache
+            .borrow()
+            .get(&room_name)
+            .filter(|(tick, _)| *tick == time)
+            .map(|(_, source_links)| source_links.clone())
+    });
+    if let Some(source_links) = cached {
+        return source_links;
+    }
+
+    let sources = room.find(find::SOURCES, None);
+    let source_links = room
+        .find(find::MY_STRUCTURES, None)
+        .iter()
+        .filter_map(|s| {
+            if let StructureObject::StructureLink(link) = s {
+                let source = sources.iter().find(|source| {
+                    link.pos()
+                        .in_range_to(source.pos(), config::SOURCE_LINK_RANGE)
+                })?;
+                return Some((link.try_id()?, source.id()));
+            }
+            None
+        })
+        .collect::<Vec<_>>();
+
+    super::SOURCE_LINK_CACHE.with(|cache| {
+        cache
+            .borrow_mut()
+            .insert(room_name, (time, source_links.clone()))
+    });
+    source_links
+}
+
+/// Returns the room's structures of the given type. Many tasks look up the same structures, so
+/// every structure in the room is found once per tick and cached by type.
+pub fn get_structures_of_type(room: &Room, structure_type: StructureType) -> Vec<StructureObject> {
+    let room_name = room.name();
+    let time = game::time();
+
+    super::STRUCTURE_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let entry = cache
+            .entry(room_name)
+            .or_insert_with(|| (0, HashMap::new()));
+        if entry.0 != time {
+            let mut structures_by_type: HashMap<StructureType, Vec<StructureObject>> =
+                HashMap::new();
+            for structure in room.find(find::STRUCTURES, None) {
+                structures_by_type
+                    .entry(structure.structure_type())
+                    .or_default()
+                    .push(structure);
+            }
+            *entry = (time, structures_by_type);
+        }
+
+        entry.1.get(&structure_type).cloned().unwrap_or_default()
+    })
+}
+
+/// Returns the structure of the given type closest to the position
+pub fn nearest_structure(
+    pos: Position,
+    room: &Room,
+    structure_type: StructureType,
+) -> Option<StructureObject> {
+    nearest_structure_with(pos, room, structure_type, |_| true)
+}
+
+/// Returns the closest structure of the given type that passes the filter
+pub fn nearest_structure_with<F>(
+    pos: Position,
+    room: &Room,
+    structure_type: StructureType,
+    filter: F,
+) -> Option<StructureObject>
+where
+    F: Fn(&StructureObject) -> bool,
+{
+    get_structures_of_type(room, structure_type)
+        .into_iter()
+        .filter(|s| filter(s))
+        .min_by_key(|s| pos.get_range_to(s.pos()))
+}
+
+/// Packs a position into a single number the same way the game packs `RoomPosition`s, so it can be
+/// kept in memory without storing the room name as a string
+pub fn pack_position(pos: Position) -> u32 {
+    pos.packed_repr()
+}
+
+pub fn unpack_position(packed: u32) -> Position {
+    Position::from_packed(packed)
+}
+
+/// Stores a packed position in the creep's memory under the key
+pub fn set_memory_position(creep: &Creep, key: &str, pos: Position) {
+    let _ = js_sys::Reflect::set(
+        &creep.memory(),
+        &JsValue::from_str(key),
+        &JsValue::from(pack_position(pos)),
+    );
+}
+
+/// Reads a position stored with `set_memory_position` back out of the creep's memory
+pub fn get_memory_position(creep: &Creep, key: &str) -> Option<Position> {
+    let packed = js_sys::Reflect::get(&creep.memory(), &JsValue::from_str(key))
+        .ok()?
+        .as_f64()?;
+    Some(unpack_position(packed as u32))
+}
+
+/// Returns true if the room's automation was turned off with `disable_room`
+pub fn is_room_disabled(room_name: RoomName) -> bool {
+    super::DISABLED_ROOMS.with(|rooms| rooms.borrow().contains(&room_name))
+}
+
+/// Returns true if the room is being abandoned with `evacuate_room`
+pub fn is_room_evacuating(room_name: RoomName) -> bool {
+    super::EVACUATING_ROOMS.with(|rooms| rooms.borrow().contains(&room_name))
+}
+
 pub fn is_mine(room: &Room) -> bool {
     room.controller()
         .map(|controller| controller.my())